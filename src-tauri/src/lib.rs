@@ -1,5 +1,97 @@
+// The app's modules, shared by the desktop binary (main.rs) and the tests.
+
+pub mod modules {
+    pub mod archive;
+    pub mod audio;
+    pub mod audio_source;
+    pub mod calibration;
+    pub mod channels;
+    pub mod commands;
+    pub mod decoding;
+    pub mod denoise;
+    pub mod devices;
+    pub mod dictionary;
+    pub mod handoff;
+    pub mod hotkeys;
+    pub mod inference;
+    pub mod language;
+    pub mod levels;
+    pub mod llm;
+    pub mod longform;
+    pub mod linux_paste;
+    pub mod loopback;
+    pub mod meter;
+    pub mod model_manager;
+    pub mod os_integration;
+    pub mod resampler;
+    pub mod state;
+    pub mod streaming;
+    pub mod transcript;
+    pub mod vad;
+    pub mod wakeword;
+}
+
+use modules::state::AppState;
+use tauri::menu::CheckMenuItem;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+pub fn re_register_shortcut(app: &AppHandle) -> Result<(), tauri_plugin_global_shortcut::Error> {
+    let state = app.state::<AppState>();
+    let mods = *state.hotkey_modifiers.lock();
+    let code = *state.hotkey_code.lock();
+
+    // Unregister all first to be safe
+    let _ = app.global_shortcut().unregister_all();
+
+    let shortcut = Shortcut::new(Some(mods), code);
+    app.global_shortcut().register(shortcut)?;
+    println!("[DEBUG] Hotkey re-registered: {:?} + {:?}", mods, code);
+
+    // A config from before duplicates were rejected may repeat a chord; the
+    // earlier hotkey keeps it
+    let capture = *state.capture_hotkey.lock();
+    if let Some((capture_mods, capture_code)) = capture {
+        let shortcut = Shortcut::new(Some(capture_mods), capture_code);
+        if app.global_shortcut().is_registered(shortcut) {
+            println!("[WARNING] Capture hotkey is taken by another hotkey, not registered.");
+        } else {
+            app.global_shortcut().register(shortcut)?;
+            println!(
+                "[DEBUG] Capture hotkey registered: {:?} + {:?}",
+                capture_mods, capture_code
+            );
+        }
+    }
+
+    let translate = *state.translate_hotkey.lock();
+    if let Some((translate_mods, translate_code)) = translate {
+        let shortcut = Shortcut::new(Some(translate_mods), translate_code);
+        if app.global_shortcut().is_registered(shortcut) {
+            println!("[WARNING] Translate hotkey is taken by another hotkey, not registered.");
+        } else {
+            app.global_shortcut().register(shortcut)?;
+            println!(
+                "[DEBUG] Translate hotkey registered: {:?} + {:?}",
+                translate_mods, translate_code
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Tray entry mirroring the translate toggle.
+pub struct TranslateTrayItem(pub CheckMenuItem<tauri::Wry>);
+
+/// Keeps the tray check mark in line with the translate toggle.
+pub fn sync_translate_tray(app: &AppHandle, enabled: bool) {
+    if let Some(item) = app.try_state::<TranslateTrayItem>() {
+        let _ = item.0.set_checked(enabled);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   // Minimal stub for library crate
   println!("Library run called - likely unused in this desktop config");
-}
\ No newline at end of file
+}
@@ -1,31 +1,42 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod modules {
-    pub mod audio;
-    pub mod commands;
-    pub mod inference;
-    pub mod llm;
-    pub mod linux_paste;
-    pub mod os_integration;
-    pub mod state;
-}
+use vibeflow_lib::modules;
+use vibeflow_lib::{re_register_shortcut, sync_translate_tray, TranslateTrayItem};
 
 use modules::{
-    audio::AudioEngine, inference::InferenceEngine, llm::ContextEngine,
-    os_integration::OSIntegration, state::AppState,
+    archive::{ArchiveSettings, SessionArchive, SessionMeta},
+    audio::{AudioEngine, SensitiveAudio, StreamHealth, StreamShared, DEFAULT_CAPTURE_SECONDS, MAX_CAPTURE_SECONDS},
+    audio_source::{DeviceStatus, StreamSupervisor},
+    calibration::{CalibrationProfiles, CalibrationRun},
+    channels::ChannelSettings,
+    decoding::DecodingSettings,
+    denoise::DenoiseSettings,
+    devices::{migrate_device_config, migrate_device_settings},
+    dictionary::{Dictionary, DICTIONARY_FILE},
+    handoff::{audio_queue, DEFAULT_CAPACITY_SAMPLES},
+    hotkeys::{HotkeyAction, HotkeyBehavior, HotkeyController, RecordingSessions},
+    inference::InferenceEngine,
+    language::LanguageSettings,
+    levels::{GainSettings, LevelReport},
+    llm::ContextEngine,
+    loopback::{MixBus, MixSettings},
+    model_manager::{ModelManager, ModelSettings},
+    os_integration::OSIntegration,
+    state::AppState,
+    transcript::{SensitiveTranscript, TranscriptSettings},
+    vad::VadSettings,
+    wakeword::WakeWordSettings,
 };
 use parking_lot::Mutex;
 use rodio::{OutputStream, Sink, Source};
 use std::sync::Arc;
-use tauri::menu::{Menu, MenuItem};
+use std::time::Instant;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_autostart::MacosLauncher;
-use tauri_plugin_global_shortcut::{
-    Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutEvent, ShortcutState,
-};
+use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut, ShortcutEvent, ShortcutState};
 use tokio::sync::mpsc;
-// use modules::audio::SensitiveAudio; (unused)
 
 #[tauri::command]
 fn ui_ready() {
@@ -53,7 +64,31 @@ async fn main() {
     let selected_device = Arc::new(Mutex::new(None));
     let hotkey_modifiers = Arc::new(Mutex::new(Modifiers::CONTROL | Modifiers::SHIFT));
     let hotkey_code = Arc::new(Mutex::new(Code::Space));
+    let hotkey_behavior = Arc::new(Mutex::new(HotkeyBehavior::default()));
+    let recording_sessions = Arc::new(Mutex::new(RecordingSessions::default()));
     let selected_model = Arc::new(Mutex::new("ggml-base.en.bin".to_string()));
+    let model_settings = Arc::new(Mutex::new(ModelSettings::default()));
+    let language_settings = Arc::new(Mutex::new(LanguageSettings::default()));
+    let vad_settings = Arc::new(Mutex::new(VadSettings::default()));
+    let decoding_settings = Arc::new(Mutex::new(DecodingSettings::default()));
+    let transcript_settings = Arc::new(Mutex::new(TranscriptSettings::default()));
+    let auto_stop_requested = Arc::new(Mutex::new(false));
+    let denoise_settings = Arc::new(Mutex::new(DenoiseSettings::default()));
+    let mut archive_settings = ArchiveSettings::default();
+    let active_device = Arc::new(Mutex::new(DeviceStatus::default()));
+    let capture_secs = Arc::new(Mutex::new(DEFAULT_CAPTURE_SECONDS));
+    let capture_request = Arc::new(Mutex::new(None));
+    let capture_hotkey = Arc::new(Mutex::new(None));
+    let translate_hotkey = Arc::new(Mutex::new(None));
+    let gain_settings = Arc::new(Mutex::new(GainSettings::default()));
+    let level_report = Arc::new(Mutex::new(LevelReport::default()));
+    let channel_settings = Arc::new(Mutex::new(ChannelSettings::default()));
+    let overlay_visible = Arc::new(Mutex::new(false));
+    let meter_watchers = Arc::new(Mutex::new(0));
+    let mix_settings = Arc::new(Mutex::new(MixSettings::default()));
+    let wake_word = Arc::new(Mutex::new(WakeWordSettings::default()));
+    let calibration_tap = Arc::new(Mutex::new(None));
+    let calibration = Arc::new(Mutex::new(CalibrationProfiles::default()));
 
     // Create a temporary app handle to get the app_data_dir without starting the app
     // Actually, we can just use std::fs since we know where it should be on Windows
@@ -65,15 +100,125 @@ async fn main() {
     let config_path = app_data.join("config.json");
     if config_path.exists() {
         if let Ok(data) = std::fs::read_to_string(&config_path) {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&data) {
+            if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(&data) {
+                // Configs from before device ids store the picked name under "device"
+                let mut changed = json.get("device").is_some()
+                    && migrate_device_config(&mut json, &AudioEngine::list_devices());
+                // ...and key per-device settings by raw name
+                changed |= migrate_device_settings(&mut json, AudioEngine::host_name());
+                if changed {
+                    if let Ok(migrated) = serde_json::to_string_pretty(&json) {
+                        let _ = std::fs::write(&config_path, migrated);
+                    }
+                }
                 if let Some(m) = json.get("model").and_then(|v| v.as_str()) {
                     *selected_model.lock() = m.to_string();
                 }
-                if let Some(d) = json.get("device").and_then(|v| v.as_str()) {
+                if let Some(d) = json
+                    .get("device_id")
+                    .or_else(|| json.get("device"))
+                    .and_then(|v| v.as_str())
+                {
                     *selected_device.lock() = Some(d.to_string());
                 }
+                if let Some(vad) = json.get("vad") {
+                    if let Ok(v) = serde_json::from_value::<VadSettings>(vad.clone()) {
+                        *vad_settings.lock() = v;
+                    }
+                }
+                if let Some(dn) = json.get("denoise") {
+                    if let Ok(d) = serde_json::from_value::<DenoiseSettings>(dn.clone()) {
+                        *denoise_settings.lock() = d;
+                    }
+                }
+                if let Some(ar) = json.get("archive") {
+                    if let Ok(a) = serde_json::from_value::<ArchiveSettings>(ar.clone()) {
+                        archive_settings = a;
+                    }
+                }
+                if let Some(ch) = json.get("channels") {
+                    if let Ok(c) = serde_json::from_value::<ChannelSettings>(ch.clone()) {
+                        *channel_settings.lock() = c;
+                    }
+                }
+                if let Some(lb) = json.get("loopback") {
+                    if let Ok(m) = serde_json::from_value::<MixSettings>(lb.clone()) {
+                        *mix_settings.lock() = m;
+                    }
+                }
+                if let Some(ww) = json.get("wake_word") {
+                    if let Ok(w) = serde_json::from_value::<WakeWordSettings>(ww.clone()) {
+                        *wake_word.lock() = w;
+                    }
+                }
+                if let Some(ms) = json.get("models") {
+                    if let Ok(m) = serde_json::from_value::<ModelSettings>(ms.clone()) {
+                        *model_settings.lock() = m;
+                    }
+                }
+                if let Some(dec) = json.get("decoding") {
+                    if let Ok(d) = serde_json::from_value::<DecodingSettings>(dec.clone()) {
+                        // A hand-edited config can hold values whisper.cpp can't run with
+                        match d.validate() {
+                            Ok(()) => *decoding_settings.lock() = d,
+                            Err(e) => println!(
+                                "[WARNING] Ignoring decoding settings, using the defaults: {}",
+                                e
+                            ),
+                        }
+                    }
+                }
+                if let Some(lang) = json.get("language") {
+                    if let Ok(l) = serde_json::from_value::<LanguageSettings>(lang.clone()) {
+                        *language_settings.lock() = l;
+                    }
+                }
+                if let Some(cal) = json.get("calibration") {
+                    if let Ok(c) = serde_json::from_value::<CalibrationProfiles>(cal.clone()) {
+                        *calibration.lock() = c;
+                    }
+                }
+                if let Some(t) = json.get("transcript") {
+                    if let Ok(t) = serde_json::from_value::<TranscriptSettings>(t.clone()) {
+                        *transcript_settings.lock() = t;
+                    }
+                }
+                if let Some(g) = json.get("gain") {
+                    if let Ok(g) = serde_json::from_value::<GainSettings>(g.clone()) {
+                        *gain_settings.lock() = g;
+                    }
+                }
+                // Older configs call it "pre_roll_seconds"
+                let capture = json.get("capture_seconds").or_else(|| json.get("pre_roll_seconds"));
+                if let Some(secs) = capture.and_then(|v| v.as_u64()) {
+                    *capture_secs.lock() = (secs as u32).min(MAX_CAPTURE_SECONDS);
+                }
+                if let Some(hk) = json.get("capture_hotkey") {
+                    let mods: Vec<String> = hk
+                        .get("modifiers")
+                        .and_then(|v| serde_json::from_value(v.clone()).ok())
+                        .unwrap_or_default();
+                    if let Some(code_str) = hk.get("code").and_then(|v| v.as_str()) {
+                        *capture_hotkey.lock() =
+                            Some(modules::commands::parse_hotkey(&mods, code_str));
+                    }
+                }
+                if let Some(hk) = json.get("translate_hotkey") {
+                    let mods: Vec<String> = hk
+                        .get("modifiers")
+                        .and_then(|v| serde_json::from_value(v.clone()).ok())
+                        .unwrap_or_default();
+                    if let Some(code_str) = hk.get("code").and_then(|v| v.as_str()) {
+                        *translate_hotkey.lock() =
+                            Some(modules::commands::parse_hotkey(&mods, code_str));
+                    }
+                }
                 // Handle hotkey loading if present in config
                 if let Some(hk) = json.get("hotkey") {
+                    // "mode"/"min_hold_ms" sit next to the chord; older configs have neither
+                    if let Ok(b) = serde_json::from_value::<HotkeyBehavior>(hk.clone()) {
+                        *hotkey_behavior.lock() = b;
+                    }
                     if let Some(mods_arr) = hk.get("modifiers").and_then(|v| v.as_array()) {
                         let mut m = Modifiers::empty();
                         for mod_v in mods_arr {
@@ -116,20 +261,85 @@ async fn main() {
         )
         .invoke_handler(tauri::generate_handler![
             modules::commands::get_audio_devices,
+            modules::commands::get_channel_settings,
+            modules::commands::set_channel_policy,
             modules::commands::set_audio_device,
             modules::commands::get_audio_device,
+            modules::commands::get_active_audio_device,
+            modules::commands::get_audio_diagnostics,
+            modules::commands::get_mix_settings,
+            modules::commands::set_mix_settings,
+            modules::commands::get_wake_word_settings,
+            modules::commands::set_wake_word_settings,
             modules::commands::save_hotkey,
             modules::commands::get_hotkey,
+            modules::commands::get_hotkey_behavior,
+            modules::commands::set_hotkey_behavior,
+            modules::commands::save_capture_hotkey,
+            modules::commands::get_capture_hotkey,
+            modules::commands::save_translate_hotkey,
+            modules::commands::get_translate_hotkey,
+            modules::commands::get_capture_seconds,
+            modules::commands::set_capture_seconds,
+            modules::commands::capture_recent_audio,
+            modules::commands::get_gain_settings,
+            modules::commands::set_gain_settings,
+            modules::commands::get_last_level_stats,
+            modules::commands::watch_audio_meter,
+            modules::commands::run_calibration_step,
+            modules::commands::finish_calibration,
+            modules::commands::get_calibration_profiles,
+            modules::commands::delete_calibration_profile,
             modules::commands::download_model,
             modules::commands::get_selected_model,
+            modules::commands::get_model_status,
+            modules::commands::set_model_settings,
+            modules::commands::get_language_settings,
+            modules::commands::set_language_settings,
+            modules::commands::set_translate_enabled,
+            modules::commands::get_decoding_settings,
+            modules::commands::set_decoding_settings,
+            modules::commands::get_transcript_settings,
+            modules::commands::set_transcript_settings,
+            modules::commands::get_dictionary,
+            modules::commands::set_dictionary,
+            modules::commands::add_dictionary_word,
+            modules::commands::remove_dictionary_word,
             modules::commands::get_onboarding_status,
             modules::commands::get_onboarding_status,
             modules::commands::complete_onboarding,
+            modules::commands::get_vad_settings,
+            modules::commands::set_vad_settings,
+            modules::commands::get_denoise_settings,
+            modules::commands::set_denoise_enabled,
+            modules::commands::get_archive_settings,
+            modules::commands::set_archive_settings,
+            modules::commands::list_archived_sessions,
+            modules::commands::delete_archived_session,
+            modules::commands::clear_archive,
+            modules::commands::get_archived_session_audio,
+            modules::commands::retranscribe_archived_session,
             ui_ready
         ])
         .setup(|app| {
             let app_data = app.path().app_data_dir()?;
-            let inference_engine = Arc::new(InferenceEngine::new(app_data));
+            let dictionary = Arc::new(Mutex::new(Dictionary::load(&app_data.join(DICTIONARY_FILE))));
+            let inference_engine = Arc::new(InferenceEngine::new(
+                ModelManager::new(app_data.clone(), selected_model.clone(), model_settings.clone()),
+                language_settings.clone(),
+                dictionary.clone(),
+                decoding_settings.clone(),
+                vad_settings.clone(),
+            ));
+            let archive = Arc::new(SessionArchive::new(&app_data, archive_settings));
+
+            // Retention also applies to sessions left over from previous runs
+            let archive_startup = archive.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = archive_startup.enforce_retention() {
+                    println!("[WARNING] Archive retention failed: {}", e);
+                }
+            });
 
             // DerJannik Branding
             println!(
@@ -146,81 +356,191 @@ async fn main() {
             println!("[INFO] Made by DerJannik | https://de.fiverr.com/s/xXgY29x");
             println!("[INFO] VibeFlow Professional initialized.");
 
-            let mods_val = *hotkey_modifiers.lock();
-            let code_val = *hotkey_code.lock();
-
             // --- CORE AUDIO REFACTOR: ALWAYS-ON STREAM ---
-            let (tx, mut rx) = mpsc::channel(100);
+            // Lossless hand-off to the inference loop (grows while Whisper is busy)
+            let (tx, mut rx) = audio_queue(DEFAULT_CAPACITY_SAMPLES);
             *tx_audio.lock() = Some(tx.clone());
-
-            let is_rec_clone = is_recording.clone();
-            let amp_clone = amplitude.clone();
-            let device_clone = selected_device.lock().clone();
+            let (capture_tx, mut capture_rx) = mpsc::channel(2);
+            let capture_dropped = Arc::new(Mutex::new(false));
+            let wake_request = Arc::new(Mutex::new(false));
+            let meter = Arc::new(Mutex::new(None));
+
+            let shared = StreamShared {
+                is_recording: is_recording.clone(),
+                amplitude: amplitude.clone(),
+                vad_settings: vad_settings.clone(),
+                auto_stop: auto_stop_requested.clone(),
+                denoise_settings: denoise_settings.clone(),
+                model_dir: app_data.clone(),
+                health: Arc::new(StreamHealth::default()),
+                capture_secs: capture_secs.clone(),
+                capture_request: capture_request.clone(),
+                capture_tx,
+                gain_settings: gain_settings.clone(),
+                channel_settings: channel_settings.clone(),
+                level_report: level_report.clone(),
+                overlay_visible: overlay_visible.clone(),
+                meter_watchers: meter_watchers.clone(),
+                meter: meter.clone(),
+                mix_settings: mix_settings.clone(),
+                mix_bus: Arc::new(MixBus::default()),
+                wake_word: wake_word.clone(),
+                calibration: calibration.clone(),
+                wake_request: wake_request.clone(),
+                capture_dropped: capture_dropped.clone(),
+                calibration_tap: calibration_tap.clone(),
+            };
+            let auto_stop_clone = auto_stop_requested.clone();
+            let wake_request_clone = wake_request.clone();
+            let device_pref = selected_device.clone();
+            let active_device_clone = active_device.clone();
+            let level_report_clone = level_report.clone();
+            let meter_clone = meter.clone();
             let app_handle = app.handle().clone();
 
             std::thread::spawn(move || {
-                let stream = AudioEngine::start_stream(
-                    tx,
-                    is_rec_clone.clone(),
-                    amp_clone.clone(),
-                    device_clone,
-                );
-                if let Ok(s) = stream {
-                    use cpal::traits::StreamTrait;
-                    let _ = s.play();
-
-                    println!(
-                        "[DEBUG] Audio Stream Started (Always-On Mode) - Listening to buffer..."
-                    );
-
-                    loop {
-                        let amp = *amp_clone.lock();
-                        // Global emit of amplitude for visualizer (even when not recording, for "alive" feel)
-                        // Or maybe only when recording? Let's keep it always for now for "Dynamic Island" feel.
-                        let _ = app_handle.emit("amplitude", amp);
-
-                        std::thread::sleep(std::time::Duration::from_millis(15));
+                // Supervised: rebuilt on device change, stream errors and device loss
+                let mut supervisor = StreamSupervisor::new(tx, shared, device_pref);
+                println!("[DEBUG] Audio Stream Supervisor started (Always-On Mode) - Listening to buffer...");
+
+                loop {
+                    if let Some(status) = supervisor.tick() {
+                        *active_device_clone.lock() = status.clone();
+                        let _ = app_handle.emit("audio_device", &status);
+                    }
+
+                    // Meter for the overlay and the visualizers, ~30 Hz and only while one is shown
+                    if let Some(frame) = meter_clone.lock().take() {
+                        let _ = app_handle.emit("audio_meter", &frame);
+                    }
+
+                    // Input level warnings (clipping / too quiet) and per-recording stats
+                    {
+                        let mut report = level_report_clone.lock();
+                        for warning in report.warnings.drain(..) {
+                            let _ = app_handle.emit("level_warning", warning);
+                        }
+                        if let Some(stats) = report.finished.take() {
+                            println!("[DEBUG] Recording levels: {:?}", stats);
+                            let _ = app_handle.emit("level_stats", &stats);
+                            report.last_session = Some(stats);
+                        }
+                    }
+
+                    // Hands-free: VAD saw enough trailing silence, stop like a second hotkey press
+                    let auto_stop = std::mem::take(&mut *auto_stop_clone.lock());
+                    if auto_stop {
+                        println!("[DEBUG] VAD auto-stop: trailing silence detected.");
+                        stop_recording(&app_handle);
+                    }
+
+                    // Hands-free start: the wake word was heard between recordings
+                    let wake = std::mem::take(&mut *wake_request_clone.lock());
+                    if wake {
+                        println!("[DEBUG] Wake word: starting recording.");
+                        start_recording(&app_handle);
                     }
-                } else {
-                    println!("[ERROR] Failed to start audio stream!");
+
+                    // Retroactive capture: the queue was full, tell the user it is lost
+                    let dropped = std::mem::take(&mut *capture_dropped.lock());
+                    if dropped {
+                        let _ = app_handle.emit("status", "Capture dropped");
+                    }
+
+                    std::thread::sleep(std::time::Duration::from_millis(15));
+                }
+            });
+
+            // --- MODEL MANAGER: preload, hot swap on model change, idle unload ---
+            let engine_models = inference_engine.clone();
+            std::thread::spawn(move || {
+                engine_models.models().maintain(true);
+                loop {
+                    std::thread::sleep(std::time::Duration::from_secs(1));
+                    engine_models.models().maintain(false);
                 }
             });
 
             // --- INFERENCE REFACTOR: LISTENER ---
             let engine = inference_engine.clone();
             let app_handle_2 = app.handle().clone();
-            let model_filename = selected_model.lock().clone();
+            let archive_clone = archive.clone();
+            let sessions_clone = recording_sessions.clone();
 
             tauri::async_runtime::spawn(async move {
-                println!(
-                    "[DEBUG] Starting transcription loop with model: {}...",
-                    model_filename
-                );
+                println!("[DEBUG] Starting transcription loop...");
 
                 loop {
-                    let (refined, command) = match engine
-                        .start_processing_loop(&mut rx, &model_filename, &app_handle_2)
-                        .await
-                    {
-                        transcript if !transcript.as_str().trim().is_empty() => {
-                            let _ = app_handle_2.emit("status", "Processing");
-                            match ContextEngine::refine_text(&transcript).await {
-                                Ok((r, c)) => (r, c),
-                                Err(_) => (transcript.as_str().to_string(), None),
-                            }
+                    let (transcript, session_audio, model_filename) = engine
+                        .start_processing_loop(
+                            &mut rx,
+                            &app_handle_2,
+                            archive_clone.is_enabled(),
+                            &sessions_clone,
+                        )
+                        .await;
+                    if transcript.as_str().trim().is_empty() {
+                        continue;
+                    }
+
+                    deliver_transcript(
+                        &app_handle_2,
+                        &archive_clone,
+                        &model_filename,
+                        transcript,
+                        session_audio,
+                    )
+                    .await;
+                }
+            });
+
+            // --- RETROACTIVE CAPTURE: ring buffer snapshots -> one-shot transcription ---
+            let engine_capture = inference_engine.clone();
+            let app_handle_capture = app.handle().clone();
+            let archive_capture = archive.clone();
+            let model_capture = selected_model.clone();
+
+            tauri::async_runtime::spawn(async move {
+                while let Some(audio) = capture_rx.recv().await {
+                    let _ = app_handle_capture.emit("status", "Processing");
+                    let model = model_capture.lock().clone();
+                    let engine = engine_capture.clone();
+                    let model_for_job = model.clone();
+                    let job = tauri::async_runtime::spawn_blocking(move || {
+                        let result = engine.transcribe(&model_for_job, audio.as_slice());
+                        (result, audio)
+                    })
+                    .await;
+                    let (result, audio) = match job {
+                        Ok(done) => done,
+                        // Whisper panicked; keep serving later captures
+                        Err(e) => {
+                            println!("[ERROR] Retroactive capture failed: {}", e);
+                            let _ = app_handle_capture.emit("status", "Ready");
+                            continue;
                         }
-                        _ => continue,
                     };
 
-                    println!("[DEBUG] Final Refined: \"{}\"", &refined);
-                    let _ = app_handle_2.emit("transcript", &refined);
-
-                    if let Some(cmd) = command {
-                        let _ = OSIntegration::execute_command(cmd);
-                    } else {
-                        let _ = OSIntegration::paste_text(&refined);
+                    match result {
+                        Ok(transcript) if !transcript.as_str().trim().is_empty() => {
+                            deliver_transcript(
+                                &app_handle_capture,
+                                &archive_capture,
+                                &model,
+                                transcript,
+                                audio,
+                            )
+                            .await;
+                        }
+                        Ok(_) => {
+                            println!("[DEBUG] Retroactive capture: no speech in buffer.");
+                            let _ = app_handle_capture.emit("status", "Ready");
+                        }
+                        Err(e) => {
+                            println!("[ERROR] Retroactive capture failed: {}", e);
+                            let _ = app_handle_capture.emit("status", "Ready");
+                        }
                     }
-                    let _ = app_handle_2.emit("status", "Ready");
                 }
             });
 
@@ -238,21 +558,61 @@ async fn main() {
                 inference_engine,
                 amplitude,
                 selected_device,
+                active_device,
                 hotkey_modifiers,
                 hotkey_code,
+                hotkey_behavior,
+                hotkey_controller: Arc::new(Mutex::new(HotkeyController::default())),
+                recording_sessions,
                 selected_model,
+                model_settings,
+                language_settings,
+                dictionary,
+                decoding_settings,
+                transcript_settings,
+                vad_settings,
+                auto_stop_requested,
+                denoise_settings,
+                archive,
+                capture_secs,
+                capture_request,
+                capture_hotkey,
+                translate_hotkey,
+                gain_settings,
+                level_report,
+                channel_settings,
+                overlay_visible,
+                meter_watchers,
+                mix_settings,
+                wake_word,
+                calibration,
+                calibration_run: Arc::new(Mutex::new(CalibrationRun::default())),
+                calibration_tap,
             };
             app.manage(state);
 
-            let shortcut = Shortcut::new(Some(mods_val), code_val);
-            let _ = app.global_shortcut().unregister_all();
-            let _ = app.global_shortcut().register(shortcut);
+            let _ = re_register_shortcut(app.handle());
 
             // System Tray Setup
             let _app_handle = app.handle().clone();
             let show_item = MenuItem::with_id(app, "show", "Show VibeFlow", true, None::<&str>)?;
+            let capture_item =
+                MenuItem::with_id(app, "capture", "Transcribe Last Seconds", true, None::<&str>)?;
+            let translating = app.state::<AppState>().language_settings.lock().translate;
+            let translate_item = CheckMenuItem::with_id(
+                app,
+                "translate",
+                "Translate to English",
+                true,
+                translating,
+                None::<&str>,
+            )?;
+            app.manage(TranslateTrayItem(translate_item.clone()));
             let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
+            let menu = Menu::with_items(
+                app,
+                &[&show_item, &capture_item, &translate_item, &quit_item],
+            )?;
 
             let tray_icon = app.default_window_icon().cloned()
                 .unwrap_or_else(|| {
@@ -271,6 +631,8 @@ async fn main() {
                             let _ = window.set_focus();
                         }
                     }
+                    "capture" => request_capture(app),
+                    "translate" => toggle_translate(app),
                     "quit" => {
                         app.exit(0);
                     }
@@ -320,35 +682,144 @@ async fn main() {
         .expect("error while running tauri application");
 }
 
+/// Refines a finished transcript, archives the session (if enabled) and pastes
+/// the result or runs the detected command. With `reject_garbage`, obvious
+/// garbage is dropped instead.
+async fn deliver_transcript(
+    app: &AppHandle,
+    archive: &Arc<SessionArchive>,
+    model_filename: &str,
+    transcript: SensitiveTranscript,
+    session_audio: SensitiveAudio,
+) {
+    let reject_garbage = app.state::<AppState>().transcript_settings.lock().reject_garbage;
+    if let Some(reason) = transcript.rejection().filter(|_| reject_garbage) {
+        println!("[WARNING] Transcript not pasted ({:?}).", reason);
+        // With the text, so the UI can offer to paste it anyway
+        let _ = app.emit(
+            "transcript_rejected",
+            serde_json::json!({ "reason": reason, "text": transcript.as_str() }),
+        );
+        let _ = app.emit("status", "Ready");
+        return;
+    }
+    let _ = app.emit("status", "Processing");
+    let (refined, command) = match ContextEngine::refine_text(&transcript).await {
+        Ok((r, c)) => (r, c),
+        Err(_) => (transcript.as_str().to_string(), None),
+    };
+
+    println!("[DEBUG] Final Refined: \"{}\"", &refined);
+
+    // Opt-in: keep an encrypted copy of the session for re-transcription
+    if archive.is_enabled() && !session_audio.as_slice().is_empty() {
+        let context = ContextEngine::get_context();
+        let meta = SessionMeta {
+            id: String::new(),
+            created_at: 0,
+            duration_ms: 0,
+            model: model_filename.to_string(),
+            app_name: context.app_name,
+            mode: format!("{:?}", context.mode),
+            language: transcript.language().map(str::to_string),
+            translated: transcript.is_translated(),
+            transcript: transcript.as_str().to_string(),
+            refined: refined.clone(),
+        };
+        let archive = archive.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            if let Err(e) = archive.store(meta, &session_audio) {
+                println!("[ERROR] Failed to archive session: {}", e);
+            }
+        });
+    }
+    let _ = app.emit("transcript", &refined);
+    if let Some(language) = transcript.language() {
+        let _ = app.emit("transcript_language", language);
+    }
+    // Raw segments with word timings and probabilities, for highlighting
+    let _ = app.emit("transcript_segments", transcript.segments());
+
+    if let Some(cmd) = command {
+        let _ = OSIntegration::execute_command(cmd);
+    } else {
+        let _ = OSIntegration::paste_text(&refined);
+    }
+    let _ = app.emit("status", "Ready");
+}
+
 fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
     let state = app.state::<AppState>();
     let mods = *state.hotkey_modifiers.lock();
     let code = *state.hotkey_code.lock();
 
     println!("[DEBUG] handle_shortcut event: {:?} for shortcut: {:?}", event, shortcut);
-    if shortcut.matches(mods, code) && event.state() == ShortcutState::Pressed {
+    // The recording hotkey wins should another slot hold the same chord
+    if shortcut.matches(mods, code) {
+        let behavior = *state.hotkey_behavior.lock();
         let recording = { *state.is_recording.lock() };
-
-        if recording {
-            stop_recording(app);
-        } else {
-            start_recording(app);
+        let pressed = event.state() == ShortcutState::Pressed;
+
+        let action = state
+            .hotkey_controller
+            .lock()
+            .handle(behavior, pressed, recording, Instant::now());
+        match action {
+            HotkeyAction::Start => start_recording(app),
+            HotkeyAction::Stop => stop_recording(app),
+            HotkeyAction::Discard => {
+                state.recording_sessions.lock().discard_current();
+                stop_recording(app);
+            }
+            HotkeyAction::Ignore => {}
+        }
+        return;
+    }
+    let capture = *state.capture_hotkey.lock();
+    if let Some((capture_mods, capture_code)) = capture {
+        if shortcut.matches(capture_mods, capture_code) {
+            if event.state() == ShortcutState::Pressed {
+                request_capture(app);
+            }
+            return;
+        }
+    }
+    let translate = *state.translate_hotkey.lock();
+    if let Some((translate_mods, translate_code)) = translate {
+        if shortcut.matches(translate_mods, translate_code)
+            && event.state() == ShortcutState::Pressed
+        {
+            toggle_translate(app);
         }
     }
 }
 
-pub fn re_register_shortcut(app: &AppHandle) -> Result<(), tauri_plugin_global_shortcut::Error> {
+/// Flips the translate toggle (tray entry and translate hotkey).
+fn toggle_translate(app: &AppHandle) {
     let state = app.state::<AppState>();
-    let mods = *state.hotkey_modifiers.lock();
-    let code = *state.hotkey_code.lock();
-
-    // Unregister all first to be safe
-    let _ = app.global_shortcut().unregister_all();
+    let enabled = !state.language_settings.lock().translate;
+    match modules::commands::apply_translate(app, enabled) {
+        Ok(()) => {
+            play_feedback_sound(if enabled { 990.0 } else { 330.0 });
+            println!(">>> VibeFlow: Translate to English {}", if enabled { "ON" } else { "OFF" });
+        }
+        Err(e) => {
+            println!("[WARNING] {}", e);
+            sync_translate_tray(app, !enabled);
+        }
+    }
+}
 
-    let shortcut = Shortcut::new(Some(mods), code);
-    app.global_shortcut().register(shortcut)?;
-    println!("[DEBUG] Hotkey re-registered: {:?} + {:?}", mods, code);
-    Ok(())
+/// Transcribe whatever the ring buffer holds right now (no-op while recording).
+fn request_capture(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if *state.is_recording.lock() {
+        return;
+    }
+    let seconds = *state.capture_secs.lock();
+    *state.capture_request.lock() = Some(seconds);
+    play_feedback_sound(660.0);
+    println!(">>> VibeFlow: Retroactive capture of the last {} s", seconds);
 }
 
 fn play_feedback_sound(frequency: f32) {
@@ -373,6 +844,9 @@ fn start_recording(app: &AppHandle) {
         return;
     }
     *recording_guard = true;
+    state.recording_sessions.lock().start();
+    // Drop any auto-stop left over from the previous session
+    *state.auto_stop_requested.lock() = false;
 
     play_feedback_sound(880.0);
     println!(">>> VibeFlow: Recording Toggle ON (Flag set to true)");
@@ -450,6 +924,7 @@ fn start_recording(app: &AppHandle) {
 
         // Show the window as early as possible on Linux to ensure it's mapped by the compositor
         let _ = overlay.show();
+        *state.overlay_visible.lock() = true;
 
         #[cfg(not(target_os = "linux"))]
         {
@@ -485,6 +960,7 @@ fn stop_recording(app: &AppHandle) {
     if let Some(overlay) = app.get_webview_window("overlay") {
         let _ = overlay.hide();
     }
+    *state.overlay_visible.lock() = false;
 
     play_feedback_sound(440.0);
     println!(">>> VibeFlow: Recording Toggle OFF (Flag set to false)");
@@ -0,0 +1,338 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::modules::audio::SensitiveAudio;
+
+const KEY_FILE: &str = "archive.key";
+const META_EXT: &str = "meta";
+const AUDIO_EXT: &str = "audio";
+const NONCE_LEN: usize = 12;
+
+/// Opt-in session archive, persisted as `"archive"` in config.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveSettings {
+    pub enabled: bool,
+    /// Keep at most this many sessions (0 = no limit).
+    pub max_sessions: usize,
+    /// Delete sessions older than this (0 = no limit).
+    pub max_age_days: u32,
+    /// Keep the archive below this size on disk (0 = no limit).
+    pub max_total_mb: u64,
+}
+
+impl Default for ArchiveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_sessions: 50,
+            max_age_days: 7,
+            max_total_mb: 200,
+        }
+    }
+}
+
+/// Everything needed to understand (and re-run) a dictation, minus the audio.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMeta {
+    pub id: String,
+    /// Unix time in milliseconds.
+    pub created_at: u64,
+    pub duration_ms: u64,
+    pub model: String,
+    pub app_name: String,
+    pub mode: String,
+    /// Language Whisper transcribed in; missing for sessions archived before it was recorded.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// The transcript is Whisper's English translation of the speech.
+    #[serde(default)]
+    pub translated: bool,
+    pub transcript: String,
+    pub refined: String,
+}
+
+// Security: transcripts stay in memory only as long as needed
+impl Drop for SessionMeta {
+    fn drop(&mut self) {
+        self.transcript.zeroize();
+        self.refined.zeroize();
+    }
+}
+
+/// Encrypted on-disk archive of 16 kHz session audio plus transcript and context.
+///
+/// Each session is two AES-256-GCM files (`<id>.meta`, `<id>.audio`), each
+/// `nonce || ciphertext`. The key is generated on first use and stored next to
+/// the archive. Deleted sessions are overwritten before unlinking; clearing the
+/// archive also destroys the key.
+pub struct SessionArchive {
+    dir: PathBuf,
+    settings: Mutex<ArchiveSettings>,
+    // Numbers the sessions stored by this process, see `id_order`
+    stored: AtomicU64,
+    // Held while the key is read, created or destroyed, so that sessions stored
+    // at the same time can't each create a key of their own
+    key_lock: Mutex<()>,
+}
+
+impl SessionArchive {
+    pub fn new(app_data_dir: &Path, settings: ArchiveSettings) -> Self {
+        Self {
+            dir: app_data_dir.join("archive"),
+            settings: Mutex::new(settings),
+            stored: AtomicU64::new(0),
+            key_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn settings(&self) -> ArchiveSettings {
+        self.settings.lock().clone()
+    }
+
+    pub fn set_settings(&self, settings: ArchiveSettings) {
+        *self.settings.lock() = settings;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.lock().enabled
+    }
+
+    /// Encrypts and writes one session, then applies the retention policy.
+    pub fn store(&self, mut meta: SessionMeta, audio: &SensitiveAudio) -> Result<String> {
+        fs::create_dir_all(&self.dir)?;
+        let cipher = self.cipher()?;
+
+        let now = unix_millis();
+        meta.id = format!("{}-{}", now, self.stored.fetch_add(1, Ordering::Relaxed));
+        meta.created_at = now;
+        meta.duration_ms = audio.as_slice().len() as u64 * 1000 / 16000;
+
+        let meta_plain = Zeroizing::new(serde_json::to_vec(&meta)?);
+        let audio_plain = Zeroizing::new(
+            audio
+                .as_slice()
+                .iter()
+                .flat_map(|s| s.to_le_bytes())
+                .collect::<Vec<u8>>(),
+        );
+
+        write_encrypted(&cipher, &self.path(&meta.id, META_EXT), &meta_plain)?;
+        write_encrypted(&cipher, &self.path(&meta.id, AUDIO_EXT), &audio_plain)?;
+        println!("[DEBUG] Archived session {} ({} ms)", meta.id, meta.duration_ms);
+
+        let id = meta.id.clone();
+        self.enforce_retention()?;
+        Ok(id)
+    }
+
+    /// Lists archived sessions, newest first.
+    pub fn list(&self) -> Result<Vec<SessionMeta>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let cipher = self.cipher()?;
+        let mut sessions = Vec::new();
+        for id in self.session_ids()? {
+            match read_encrypted(&cipher, &self.path(&id, META_EXT)) {
+                Ok(plain) => {
+                    if let Ok(meta) = serde_json::from_slice::<SessionMeta>(&plain) {
+                        sessions.push(meta);
+                    }
+                }
+                Err(e) => println!("[WARNING] Could not read archived session {}: {}", id, e),
+            }
+        }
+        sessions.sort_by_key(|s| std::cmp::Reverse(id_order(&s.id)));
+        Ok(sessions)
+    }
+
+    pub fn load_audio(&self, id: &str) -> Result<SensitiveAudio> {
+        let cipher = self.cipher()?;
+        let plain = read_encrypted(&cipher, &self.path(id, AUDIO_EXT))?;
+        let samples = plain
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(SensitiveAudio::new(samples))
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        secure_delete(&self.path(id, META_EXT))?;
+        secure_delete(&self.path(id, AUDIO_EXT))?;
+        Ok(())
+    }
+
+    /// Wipes every session and the key; the next stored session gets a new one.
+    /// Overwriting is no reliable erase on SSDs and copy-on-write filesystems,
+    /// but blocks that survive can't be decrypted without the key.
+    pub fn clear(&self) -> Result<()> {
+        let _key = self.key_lock.lock();
+        for id in self.session_ids()? {
+            self.delete(&id)?;
+        }
+        secure_delete(&self.dir.join(KEY_FILE))
+    }
+
+    /// Deletes sessions beyond the count, age and size limits (oldest first).
+    pub fn enforce_retention(&self) -> Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        let settings = self.settings();
+
+        let mut ids = self.session_ids()?;
+        ids.sort_by_key(|id| std::cmp::Reverse(id_order(id)));
+
+        let now = unix_millis();
+        let max_age_ms = settings.max_age_days as u64 * 24 * 60 * 60 * 1000;
+        let max_bytes = settings.max_total_mb * 1024 * 1024;
+        let mut total_bytes = 0u64;
+
+        for (index, id) in ids.iter().enumerate() {
+            let (created, _) = id_order(id);
+            total_bytes += self.session_size(id);
+
+            let too_many = settings.max_sessions > 0 && index >= settings.max_sessions;
+            let too_old = settings.max_age_days > 0 && now.saturating_sub(created) > max_age_ms;
+            let too_big = settings.max_total_mb > 0 && total_bytes > max_bytes;
+
+            if too_many || too_old || too_big {
+                println!("[DEBUG] Retention: deleting archived session {}", id);
+                self.delete(id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn session_ids(&self) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        if !self.dir.exists() {
+            return Ok(ids);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some(META_EXT) {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(stem.to_string());
+                }
+            }
+        }
+        Ok(ids)
+    }
+
+    fn session_size(&self, id: &str) -> u64 {
+        [META_EXT, AUDIO_EXT]
+            .iter()
+            .filter_map(|ext| fs::metadata(self.path(id, ext)).ok())
+            .map(|m| m.len())
+            .sum()
+    }
+
+    fn path(&self, id: &str, ext: &str) -> PathBuf {
+        // ids are generated by us, but they also arrive via Tauri commands
+        let safe: String = id
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == '-')
+            .collect();
+        self.dir.join(format!("{}.{}", safe, ext))
+    }
+
+    fn cipher(&self) -> Result<Aes256Gcm> {
+        let _key = self.key_lock.lock();
+        let key_path = self.dir.join(KEY_FILE);
+        let mut key_bytes = if key_path.exists() {
+            fs::read(&key_path)?
+        } else {
+            fs::create_dir_all(&self.dir)?;
+            let key = Aes256Gcm::generate_key(OsRng);
+            write_private(&key_path, key.as_slice())?;
+            key.to_vec()
+        };
+        if key_bytes.len() != 32 {
+            key_bytes.zeroize();
+            return Err(anyhow!("Archive key at {:?} is corrupt", key_path));
+        }
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+        key_bytes.zeroize();
+        Ok(cipher)
+    }
+}
+
+// Ids are "<created ms>-<n>", with n counting up per process so two sessions in
+// the same millisecond don't overwrite each other; older ones are just "<ms>".
+// Sorting by (ms, n) sorts by age.
+fn id_order(id: &str) -> (u64, u64) {
+    let mut parts = id.splitn(2, '-').map(|p| p.parse::<u64>().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn write_encrypted(cipher: &Aes256Gcm, path: &Path, plain: &[u8]) -> Result<()> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plain)
+        .map_err(|_| anyhow!("Archive encryption failed"))?;
+    let mut data = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(nonce.as_slice());
+    data.extend_from_slice(&ciphertext);
+    write_private(path, &data)
+}
+
+fn read_encrypted(cipher: &Aes256Gcm, path: &Path) -> Result<Zeroizing<Vec<u8>>> {
+    let data = fs::read(path)?;
+    if data.len() < NONCE_LEN {
+        return Err(anyhow!("Archive file {:?} is truncated", path));
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Archive file {:?} failed authentication", path))?;
+    Ok(Zeroizing::new(plain))
+}
+
+// Security: archive files and the key are readable by the current user only
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// Security: overwrite before unlinking. On SSDs/CoW filesystems the old blocks may
+// survive, which is why the content is encrypted in the first place.
+fn secure_delete(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let len = fs::metadata(path)?.len() as usize;
+    {
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        file.write_all(&vec![0u8; len])?;
+        file.sync_all()?;
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -3,18 +3,34 @@ use cpal::traits::{DeviceTrait, HostTrait};
 use parking_lot::Mutex;
 use std::collections::VecDeque;
 use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-// VAD & Noise Suppression would be integrated here
-// For Phase 1, we will implement the Ring Buffer logic first as the Core "Rewind" mechanic.
-// The VAD integration with 'tract' requires loading an ONNX model file.
-// To keep Phase 1 pure "Core Audio Refactor", we will implement the Ring Buffer and prepare the struct for VAD.
+use crate::modules::channels::{ChannelMixer, ChannelSettings};
+use crate::modules::denoise::{Denoiser, DenoiseSettings, DENOISE_SAMPLE_RATE};
+use crate::modules::devices::{self, DeviceInfo, DeviceKind};
+use crate::modules::handoff::AudioSender;
+use crate::modules::calibration::CalibrationProfiles;
+use crate::modules::levels::{self, GainSettings, LevelMonitor, LevelReport};
+use crate::modules::loopback::{self, MixBus, MixSettings};
+use crate::modules::meter::{Meter, MeterFrame};
+use crate::modules::resampler::StreamResampler;
+use crate::modules::vad::{AutoStopDetector, VadSettings};
+use crate::modules::wakeword::{WakeWordListener, WakeWordSettings};
+// The Ring Buffer is the Core "Rewind" mechanic. A VAD (WebRTC, or Silero ONNX via tract)
+// runs on the resampled 16 kHz stream for hands-free auto-stop (see vad.rs),
+// RNNoise optionally cleans it up (see denoise.rs). Between recordings an optional
+// wake-word model listens on the same stream (see wakeword.rs).
 
 // Audio constants
 const SAMPLE_RATE: u32 = 16000;
-const PRE_RECORD_SECONDS: usize = 3;
-// 16000 samples/sec * 3 sec = 48000 samples
-const RING_BUFFER_SIZE: usize = (SAMPLE_RATE as usize) * PRE_RECORD_SECONDS;
+// Audio from before the hotkey that starts every recording
+const PRE_ROLL_SECONDS: usize = 3;
+// Retroactive capture length is configurable ("capture_seconds" in config.json)
+pub const DEFAULT_CAPTURE_SECONDS: u32 = 30;
+// 16000 samples/sec * 300 sec = 4.8M samples (~19 MB)
+pub const MAX_CAPTURE_SECONDS: u32 = 300;
 
 // Security: Protected Audio Buffer
 #[derive(zeroize::Zeroize, zeroize::ZeroizeOnDrop)]
@@ -38,95 +54,42 @@ impl SensitiveAudio {
 pub struct AudioEngine;
 
 impl AudioEngine {
+    /// Opens an input stream and returns it together with the device that was
+    /// actually opened, which may differ from `device` after auto-healing.
+    /// `device` is a device id (older configs: a raw or display name).
     pub fn start_stream(
-        tx: mpsc::Sender<SensitiveAudio>,
-        is_recording: Arc<Mutex<bool>>,
-        amplitude: Arc<Mutex<f32>>,
-        device_name: Option<String>,
-    ) -> Result<cpal::Stream> {
+        tx: AudioSender,
+        shared: StreamShared,
+        device: Option<String>,
+    ) -> Result<(cpal::Stream, DeviceInfo)> {
         let host = cpal::default_host();
+        let default_name = host.default_input_device().and_then(|d| d.name().ok());
 
         // 3. Define the Stream Builder (Closure)
-        let build_stream_fn = |device: &cpal::Device| -> Result<cpal::Stream> {
+        let build_stream_fn = |device: &cpal::Device| -> Result<(cpal::Stream, DeviceInfo)> {
             let name = device.name().unwrap_or("unknown".to_string());
             println!("[DEBUG] Trying device: {}", name);
-            
-            // SMART CONFIG SELECTION (Universal)
-            let supported_configs = device.supported_input_configs()?;
-            let mut selected_config = None;
-            
-            for config_range in supported_configs {
-                 let entry = config_range.with_max_sample_rate();
-                 if selected_config.is_none() {
-                     selected_config = Some(entry);
-                     continue;
-                 }
-                 let current = selected_config.as_ref().unwrap();
-                 if entry.sample_format() == cpal::SampleFormat::F32 && current.sample_format() != cpal::SampleFormat::F32 {
-                     selected_config = Some(entry);
-                 }
-            }
+            let id = devices::device_id(host.id().name(), &name);
+
+            let config = Self::select_input_config(device)?;
 
-            let config = selected_config
-                .ok_or_else(|| anyhow!("No supported input config found"))?;
-                
-            let source_sample_rate = config.sample_rate().0 as f32;
+            let source_sample_rate = config.sample_rate().0;
             let source_channels = config.channels() as usize;
-            let target_sample_rate = 16000.0;
-            
-            let tx_clone = tx.clone();
-            let amp_clone = amplitude.clone();
-            let is_rec_clone = is_recording.clone();
-            let mut resample_buffer = Vec::new();
-            let mut last_sample_pos = 0.0;
-            let mut ring_buffer: VecDeque<f32> = VecDeque::with_capacity(RING_BUFFER_SIZE);
-            let mut was_recording = false;
-            let err_fn = |err| println!("[ERROR] Audio stream error: {}", err);
-
-            match config.sample_format() {
-                cpal::SampleFormat::F32 => {
-                    device.build_input_stream(
-                        &config.into(),
-                        move |data: &[f32], _: &_| {
-                             Self::process_audio_chunk(
-                                 data, &tx_clone, &is_rec_clone, &amp_clone, 
-                                 source_channels, source_sample_rate, target_sample_rate, 
-                                 &mut resample_buffer, &mut last_sample_pos, &mut ring_buffer, &mut was_recording
-                            );
-                        },
-                        err_fn, None
-                    ).map_err(|e| anyhow!(e))
-                },
-                cpal::SampleFormat::I16 => {
-                    device.build_input_stream(
-                        &config.into(),
-                        move |data: &[i16], _: &_| {
-                            let float_data: Vec<f32> = data.iter().map(|&x| x as f32 / 32768.0).collect();
-                             Self::process_audio_chunk(
-                                 &float_data, &tx_clone, &is_rec_clone, &amp_clone, 
-                                 source_channels, source_sample_rate, target_sample_rate, 
-                                 &mut resample_buffer, &mut last_sample_pos, &mut ring_buffer, &mut was_recording
-                            );
-                        },
-                        err_fn, None
-                    ).map_err(|e| anyhow!(e))
-                },
-                cpal::SampleFormat::U16 => {
-                     device.build_input_stream(
-                        &config.into(),
-                        move |data: &[u16], _: &_| {
-                            let float_data: Vec<f32> = data.iter().map(|&x| (x as f32 - 32768.0) / 32768.0).collect();
-                             Self::process_audio_chunk(
-                                 &float_data, &tx_clone, &is_rec_clone, &amp_clone, 
-                                 source_channels, source_sample_rate, target_sample_rate, 
-                                 &mut resample_buffer, &mut last_sample_pos, &mut ring_buffer, &mut was_recording
-                            );
-                        },
-                        err_fn, None
-                    ).map_err(|e| anyhow!(e))
-                }
-                _ => Err(anyhow!("Unsupported sample format"))
-            }
+
+            let mut pipeline = AudioPipeline::new(
+                tx.clone(), shared.clone(), id, source_channels, source_sample_rate,
+            )?;
+            // Device unplugged / backend gone: let the supervisor rebuild the stream
+            let health = shared.health.clone();
+            let err_fn = move |err| {
+                println!("[ERROR] Audio stream error: {}", err);
+                health.failed.store(true, Ordering::Relaxed);
+            };
+
+            let stream = Self::build_input_stream(device, config, move |data| pipeline.process(data), err_fn)?;
+            let info = Self::describe(&host, device, default_name.as_deref())
+                .ok_or_else(|| anyhow!("Could not describe device {}", name))?;
+            Ok((stream, info))
         };
 
         // 4. Execute Device Selection Strategy (STRICT OS SEPARATION)
@@ -134,12 +97,14 @@ impl AudioEngine {
         #[cfg(target_os = "linux")]
         {
             println!("[DEBUG] Using LINUX-Specific Device Selection Strategy (Auto-Healing)");
-            // A. Specific Device
-            if let Some(name) = device_name {
-                 let device = host.input_devices()?
-                    .find(|x| x.name().map(|n| n == name).unwrap_or(false))
-                    .ok_or(anyhow!("Device not found"))?;
-                return build_stream_fn(&device);
+            // A. Specific Device (falls through to B/C when it is gone, e.g. unplugged)
+            if let Some(reference) = device {
+                let attempt = Self::find_input_device(&host, &reference).map(|d| build_stream_fn(&d));
+                match attempt {
+                    Some(Ok(stream)) => return Ok(stream),
+                    Some(Err(e)) => println!("[WARNING] Selected device \"{}\" failed: {}", reference, e),
+                    None => println!("[WARNING] Selected device \"{}\" not found", reference),
+                }
             }
             // B. Default
             println!("[DEBUG] Trying Default Device...");
@@ -154,7 +119,7 @@ impl AudioEngine {
             if let Ok(devices) = host.input_devices() {
                 for device in devices {
                     if let Ok(stream) = build_stream_fn(&device) {
-                         println!("[DEBUG] Auto-Healing connected to: {:?}", device.name().unwrap_or_default());
+                         println!("[DEBUG] Auto-Healing connected to: {:?}", stream.1);
                          return Ok(stream);
                     }
                 }
@@ -166,10 +131,9 @@ impl AudioEngine {
         {
             println!("[DEBUG] Using WINDOWS-Specific Device Selection Strategy (Standard)");
             // A. Specific Device
-            if let Some(name) = device_name {
-                 let device = host.input_devices()?
-                    .find(|x| x.name().map(|n| n == name).unwrap_or(false))
-                    .ok_or(anyhow!("Device not found"))?;
+            if let Some(reference) = device {
+                let device = Self::find_input_device(&host, &reference)
+                    .ok_or_else(|| anyhow!("Device not found: {}", reference))?;
                 return build_stream_fn(&device);
             }
             // B. Default Only (Standard behavior)
@@ -181,135 +145,518 @@ impl AudioEngine {
         #[cfg(target_os = "macos")]
         {
              // MacOS Strategy (Similar to Windows)
+            let _ = device;
             let device = host.default_input_device()
                 .ok_or_else(|| anyhow!("No default input device found"))?;
             build_stream_fn(&device)
         }
     }
 
-    pub fn list_input_devices() -> Result<Vec<String>> {
+    /// Builds an input stream that hands every block to `on_data` as f32,
+    /// whatever the device's native sample format.
+    fn build_input_stream(
+        device: &cpal::Device,
+        config: cpal::SupportedStreamConfig,
+        mut on_data: impl FnMut(&[f32]) + Send + 'static,
+        err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+    ) -> Result<cpal::Stream> {
+        match config.sample_format() {
+            cpal::SampleFormat::F32 => {
+                device.build_input_stream(
+                    &config.into(),
+                    move |data: &[f32], _: &_| {
+                        on_data(data);
+                    },
+                    err_fn, None
+                ).map_err(|e| anyhow!(e))
+            },
+            cpal::SampleFormat::I16 => {
+                device.build_input_stream(
+                    &config.into(),
+                    move |data: &[i16], _: &_| {
+                        let float_data: Vec<f32> = data.iter().map(|&x| x as f32 / 32768.0).collect();
+                        on_data(&float_data);
+                    },
+                    err_fn, None
+                ).map_err(|e| anyhow!(e))
+            },
+            cpal::SampleFormat::U16 => {
+                device.build_input_stream(
+                    &config.into(),
+                    move |data: &[u16], _: &_| {
+                        let float_data: Vec<f32> = data.iter().map(|&x| (x as f32 - 32768.0) / 32768.0).collect();
+                        on_data(&float_data);
+                    },
+                    err_fn, None
+                ).map_err(|e| anyhow!(e))
+            }
+            _ => Err(anyhow!("Unsupported sample format"))
+        }
+    }
+
+    // SMART CONFIG SELECTION (Universal): highest rate, F32 preferred
+    fn select_input_config(device: &cpal::Device) -> Result<cpal::SupportedStreamConfig> {
+        let supported_configs = device.supported_input_configs()?;
+        let mut selected_config: Option<cpal::SupportedStreamConfig> = None;
+
+        for config_range in supported_configs {
+            let entry = config_range.with_max_sample_rate();
+            match &selected_config {
+                None => selected_config = Some(entry),
+                Some(current) => {
+                    if entry.sample_format() == cpal::SampleFormat::F32
+                        && current.sample_format() != cpal::SampleFormat::F32
+                    {
+                        selected_config = Some(entry);
+                    }
+                }
+            }
+        }
+
+        selected_config.ok_or_else(|| anyhow!("No supported input config found"))
+    }
+
+    /// Feeds `duration_ms` of synthetic 16 kHz audio (a quiet 220 Hz tone) into the
+    /// channel in real time, in 100 ms chunks. Lets the inference side be exercised
+    /// without any audio hardware.
+    pub async fn simulate_recording(tx: AudioSender, duration_ms: u64) {
+        let chunk_ms = 100;
+        let chunk_len = SAMPLE_RATE as usize * chunk_ms as usize / 1000;
+        let mut t = 0usize;
+
+        for _ in 0..(duration_ms / chunk_ms) {
+            let chunk: Vec<f32> = (0..chunk_len)
+                .map(|i| {
+                    let n = (t + i) as f32 / SAMPLE_RATE as f32;
+                    0.1 * (2.0 * std::f32::consts::PI * 220.0 * n).sin()
+                })
+                .collect();
+            t += chunk_len;
+
+            tx.push(SensitiveAudio::new(chunk));
+            tokio::time::sleep(std::time::Duration::from_millis(chunk_ms)).await;
+        }
+    }
+
+    /// Name of the default host, the first part of its devices' ids.
+    pub fn host_name() -> &'static str {
+        cpal::default_host().id().name()
+    }
+
+    /// All input devices of the default host, default device first.
+    pub fn list_devices() -> Vec<DeviceInfo> {
         let host = cpal::default_host();
+        println!("[DEBUG] CPAL Host: {}", host.id().name());
         let devices = match host.input_devices() {
             Ok(d) => d,
             Err(e) => {
                 println!("[ERROR] Failed to get input devices: {}", e);
-                return Ok(vec!["Default Microphone".to_string()]);
+                return Vec::new();
             }
         };
+        let default_name = host.default_input_device().and_then(|d| d.name().ok());
 
-        let mut names = Vec::new();
-        println!("[DEBUG] CPAL Host: {}", host.id().name());
-
-        for device in devices {
-            if let Ok(name) = device.name() {
-                println!("[DEBUG] RAW DEVICE NAME: \"{}\"", name);
-
-                let mut display_name = name.clone();
-                if name.to_uppercase().contains("Q9") {
-                    display_name = "Q9 Microphone 🎙️".to_string();
-                } else if name.starts_with("sysdefault:CARD=") || name.starts_with("default:CARD=")
-                {
-                    display_name = name
-                        .replace("sysdefault:CARD=", "")
-                        .replace("default:CARD=", "")
-                        .split(',')
-                        .next()
-                        .unwrap_or(&name)
-                        .to_string();
+        let mut infos: Vec<DeviceInfo> = devices
+            .filter_map(|device| {
+                let info = Self::describe(&host, &device, default_name.as_deref());
+                if info.is_none() {
+                    println!("[DEBUG] Could not query a device, skipping it");
                 }
+                info
+            })
+            .collect();
+        infos.sort_by_key(|d| !d.is_default);
+        // System audio (output monitors) after the microphones
+        infos.extend(loopback::list_loopback_devices());
+
+        println!(
+            "[DEBUG] Input devices: {:?}",
+            infos.iter().map(|d| &d.raw_name).collect::<Vec<_>>()
+        );
+        infos
+    }
 
-                if !names.contains(&display_name) {
-                    names.push(display_name);
+    fn describe(
+        host: &cpal::Host,
+        device: &cpal::Device,
+        default_name: Option<&str>,
+    ) -> Option<DeviceInfo> {
+        let raw_name = device.name().ok()?;
+        let host_name = host.id().name().to_string();
+
+        let mut channels = Vec::new();
+        let mut ranges = Vec::new();
+        let mut sample_formats = Vec::new();
+        if let Ok(configs) = device.supported_input_configs() {
+            for range in configs {
+                channels.push(range.channels());
+                ranges.push((range.min_sample_rate().0, range.max_sample_rate().0));
+                let format = range.sample_format().to_string();
+                if !sample_formats.contains(&format) {
+                    sample_formats.push(format);
                 }
-            } else {
-                println!("[DEBUG] Could not query name for a device");
             }
         }
+        channels.sort_unstable();
+        channels.dedup();
+        let stream_channels = Self::select_input_config(device)
+            .map(|c| c.channels())
+            .unwrap_or(0);
+
+        Some(DeviceInfo {
+            id: devices::device_id(&host_name, &raw_name),
+            display_name: devices::display_name(&raw_name),
+            is_default: default_name == Some(raw_name.as_str()),
+            raw_name,
+            host: host_name,
+            channels,
+            stream_channels,
+            sample_rates: devices::rates_in_ranges(&ranges),
+            sample_formats,
+            kind: DeviceKind::Microphone,
+        })
+    }
 
-        if names.is_empty() {
-            names.push("Default Microphone".to_string());
-        }
+    /// Looks a device up by id, falling back to raw and then display name
+    /// (what configs stored before device ids).
+    fn find_input_device(host: &cpal::Host, reference: &str) -> Option<cpal::Device> {
+        let host_name = host.id().name();
+        let named: Vec<(cpal::Device, String)> = host
+            .input_devices()
+            .ok()?
+            .filter_map(|d| d.name().ok().map(|n| (d, n)))
+            .collect();
+
+        let position = named
+            .iter()
+            .position(|(_, n)| devices::device_id(host_name, n) == reference)
+            .or_else(|| named.iter().position(|(_, n)| n == reference))
+            .or_else(|| named.iter().position(|(_, n)| devices::display_name(n) == reference))?;
+        named.into_iter().nth(position).map(|(d, _)| d)
+    }
 
-        println!("[DEBUG] Final selection for UI: {:?}", names);
-        Ok(names)
+    /// Whether the device `reference` (id or name) is currently present.
+    pub fn input_device_available(reference: &str) -> bool {
+        if let Some(monitor) = loopback::monitor_name(reference) {
+            return loopback::list_loopback_devices()
+                .iter()
+                .any(|d| d.raw_name == monitor);
+        }
+        Self::find_input_device(&cpal::default_host(), reference).is_some()
     }
+}
 
-    fn process_audio_chunk(
-        data: &[f32],
-        tx: &mpsc::Sender<SensitiveAudio>,
-        is_rec: &Arc<Mutex<bool>>,
-        amp: &Arc<Mutex<f32>>,
+/// Liveness of the current input stream, watched by the stream supervisor.
+#[derive(Default)]
+pub struct StreamHealth {
+    /// Set by cpal's error callback (device lost, backend error).
+    pub failed: AtomicBool,
+    /// Incremented on every processed block; a stalled counter means a dead stream.
+    pub blocks: AtomicU64,
+}
+
+/// Shared handles the always-on stream reads and writes from its callback.
+#[derive(Clone)]
+pub struct StreamShared {
+    pub is_recording: Arc<Mutex<bool>>,
+    pub amplitude: Arc<Mutex<f32>>,
+    pub vad_settings: Arc<Mutex<VadSettings>>,
+    pub auto_stop: Arc<Mutex<bool>>,
+    pub denoise_settings: Arc<Mutex<DenoiseSettings>>,
+    /// App data dir, where optional models (e.g. Silero VAD) live.
+    pub model_dir: PathBuf,
+    pub health: Arc<StreamHealth>,
+    /// How much audio the ring buffer keeps for retroactive capture, in seconds.
+    /// Recordings only ever get the last `PRE_ROLL_SECONDS` of it.
+    pub capture_secs: Arc<Mutex<u32>>,
+    /// Retroactive capture: `Some(seconds)` hands the newest `seconds` of the ring
+    /// buffer to `capture_tx` on the next block (ignored while recording).
+    pub capture_request: Arc<Mutex<Option<u32>>>,
+    pub capture_tx: mpsc::Sender<SensitiveAudio>,
+    /// Set when a capture was dropped because earlier ones are still queued;
+    /// the UI loop reports it.
+    pub capture_dropped: Arc<Mutex<bool>>,
+    pub gain_settings: Arc<Mutex<GainSettings>>,
+    pub channel_settings: Arc<Mutex<ChannelSettings>>,
+    /// Level warnings and per-recording stats for the UI loop to emit.
+    pub level_report: Arc<Mutex<LevelReport>>,
+    /// The meter only runs while the overlay is on screen...
+    pub overlay_visible: Arc<Mutex<bool>>,
+    /// ...or a visualizer in the main window subscribed to it.
+    pub meter_watchers: Arc<Mutex<usize>>,
+    /// Newest meter frame, taken (and emitted) by the UI loop.
+    pub meter: Arc<Mutex<Option<MeterFrame>>>,
+    pub mix_settings: Arc<Mutex<MixSettings>>,
+    /// System audio waiting to be mixed into the microphone stream.
+    pub mix_bus: Arc<MixBus>,
+    pub wake_word: Arc<Mutex<WakeWordSettings>>,
+    /// Per-device levels from the calibration wizard, applied over the gain/VAD settings.
+    pub calibration: Arc<Mutex<CalibrationProfiles>>,
+    /// Set when the wake word was heard; the UI loop starts the recording.
+    pub wake_request: Arc<Mutex<bool>>,
+    /// Calibration wizard: gets a copy of the 16 kHz stream, before the mix and the AGC.
+    pub calibration_tap: Arc<Mutex<Option<AudioSender>>>,
+}
+
+/// Per-stream processing state, owned by whichever `AudioSource` feeds it
+/// (cpal callback, file or stdin thread).
+// Downmix (channel policy) -> band-limited resample (optionally via 48 kHz denoise) -> 16 kHz -> ring buffer / VAD / channel.
+pub struct AudioPipeline {
+    tx: AudioSender,
+    shared: StreamShared,
+    // Key of the per-device settings (channels, denoise, calibration)
+    device_id: String,
+    channels: usize,
+    src_rate: u32,
+    mixer: ChannelMixer,
+    resampler: StreamResampler,
+    // Denoise path: RNNoise only runs at 48 kHz, so resample there and back
+    denoise_in: StreamResampler,
+    denoise_out: StreamResampler,
+    // Created up front: RNNoise's state is too big to allocate in the callback
+    denoiser: Denoiser,
+    denoising: bool,
+    ring_buf: VecDeque<f32>,
+    was_rec: bool,
+    vad: AutoStopDetector,
+    levels: LevelMonitor,
+    meter: Meter,
+    wake: WakeWordListener,
+    // A wake word was heard and its recording has not started yet / is running.
+    // Wake-word sessions always end on trailing silence, there is no key to press.
+    wake_pending: bool,
+    wake_session: bool,
+}
+
+impl AudioPipeline {
+    pub fn new(
+        tx: AudioSender,
+        shared: StreamShared,
+        device_id: String,
         channels: usize,
-        src_rate: f32,
-        dst_rate: f32,
-        resample_buf: &mut Vec<f32>,
-        last_pos: &mut f32,
-        ring_buf: &mut VecDeque<f32>,
-        was_rec: &mut bool
-    ) {
-        let recording_now = *is_rec.lock();
+        src_rate: u32,
+    ) -> Result<Self> {
+        let vad = AutoStopDetector::new(&shared.vad_settings.lock(), shared.model_dir.clone());
+        let wake = WakeWordListener::new(shared.model_dir.clone());
+        Ok(Self {
+            tx,
+            shared,
+            device_id,
+            channels,
+            src_rate,
+            mixer: ChannelMixer::new(channels, src_rate),
+            resampler: StreamResampler::new(src_rate, SAMPLE_RATE)?,
+            denoise_in: StreamResampler::new(src_rate, DENOISE_SAMPLE_RATE)?,
+            denoise_out: StreamResampler::new(DENOISE_SAMPLE_RATE, SAMPLE_RATE)?,
+            denoiser: Denoiser::new(),
+            denoising: false,
+            ring_buf: VecDeque::new(),
+            was_rec: false,
+            vad,
+            levels: LevelMonitor::new(),
+            meter: Meter::new(),
+            wake,
+            wake_pending: false,
+            wake_session: false,
+        })
+    }
 
-        // 1. Amplitude (RMS)
-        let sum: f32 = data.iter().map(|&x| x * x).sum();
-        let rms = if !data.is_empty() {
-            (sum / data.len() as f32).sqrt()
-        } else {
-            0.0
-        };
-        *amp.lock() = rms;
+    /// Takes interleaved samples at the source's rate and channel count.
+    pub fn process(&mut self, data: &[f32]) {
+        let recording_now = *self.shared.is_recording.lock();
+        self.shared.health.blocks.fetch_add(1, Ordering::Relaxed);
 
-        // 2. Downmix
-        let mut mono_data = Vec::with_capacity(data.len() / channels);
-        for chunk in data.chunks_exact(channels) {
-            let sum: f32 = chunk.iter().sum();
-            mono_data.push(sum / channels as f32);
+        // 1. Amplitude (RMS)
+        let rms = levels::rms(data);
+        *self.shared.amplitude.lock() = rms;
+
+        // Level stats and warnings follow the recording flag (raw input levels)
+        // Global gain settings, adjusted by the device's calibration profile
+        let gain_settings = self
+            .shared
+            .calibration
+            .lock()
+            .gain_for(&self.device_id, &self.shared.gain_settings.lock());
+        if recording_now && !self.levels.is_recording() {
+            self.levels.start_session();
+        } else if !recording_now && self.levels.is_recording() {
+            let stats = self.levels.finish_session();
+            self.shared.level_report.lock().finished = Some(stats);
+        }
+        let samples_per_second = (self.src_rate as usize * self.channels) as f32;
+        if let Some(warning) = self.levels.observe_input(data, rms, samples_per_second, &gain_settings) {
+            println!("[WARNING] Input level: {:?}", warning);
+            self.shared.level_report.lock().warnings.push(warning);
         }
 
-        // 3. Resample
-        let mut processed_chunk = Vec::new();
-        let ratio = src_rate / dst_rate;
-
-        for sample in mono_data {
-            resample_buf.push(sample);
+        // 2. Downmix (per-device channel policy)
+        let policy = self
+            .shared
+            .channel_settings
+            .lock()
+            .policy_for(&self.device_id);
+        let mono_data = self.mixer.mix(data, policy);
+
+        // 3. Resample (+ optional noise suppression)
+        let denoise = self
+            .shared
+            .denoise_settings
+            .lock()
+            .enabled_for(&self.device_id);
+
+        if denoise != self.denoising {
+            self.denoising = denoise;
+            println!(
+                "[DEBUG] Noise suppression {} for {}",
+                if denoise { "enabled" } else { "disabled" },
+                self.device_id
+            );
+            // Both paths start clean: input left in the one that was idle is from
+            // before the switch and would be spliced into the stream
+            self.resampler.reset();
+            self.denoise_in.reset();
+            self.denoise_out.reset();
+            self.denoiser.reset();
+        }
 
-            while *last_pos < resample_buf.len() as f32 - 1.0 {
-                let idx = *last_pos as usize;
-                let frac = *last_pos - idx as f32;
+        let mut processed_chunk = if denoise {
+            let upsampled = self.denoise_in.process(&mono_data);
+            let clean = self.denoiser.process(&upsampled);
+            self.denoise_out.process(&clean)
+        } else {
+            self.resampler.process(&mono_data)
+        };
 
-                let s1 = resample_buf[idx];
-                let s2 = resample_buf[idx + 1];
-                let interpolated = s1 + (s2 - s1) * frac;
+        if let Some(tap) = self.shared.calibration_tap.lock().as_ref() {
+            tap.push(SensitiveAudio::new(processed_chunk.clone()));
+        }
 
-                processed_chunk.push(interpolated);
-                *last_pos += ratio;
+        // Mic + system audio: mixed at 16 kHz, so everything below sees one stream
+        {
+            // Locked, not cloned: the monitor name would be an allocation per block
+            let mix = self.shared.mix_settings.lock();
+            if mix.enabled {
+                self.shared.mix_bus.mix_into(&mut processed_chunk, &mix);
             }
         }
 
-        let drain_amt = last_pos.floor() as usize;
-        if drain_amt > 0 && resample_buf.len() > drain_amt {
-            resample_buf.drain(0..drain_amt);
-            *last_pos -= drain_amt as f32;
+        // Overlay meter: input level as resampled, before the AGC evens it out
+        if *self.shared.overlay_visible.lock() || *self.shared.meter_watchers.lock() > 0 {
+            if let Some(frame) = self.meter.push(&processed_chunk) {
+                *self.shared.meter.lock() = Some(frame);
+            }
         }
 
+        // 4. Automatic gain control (before ring buffer, VAD and Whisper)
+        self.levels.apply_gain(&mut processed_chunk, &gain_settings);
+
         if !processed_chunk.is_empty() {
              // --- REWIND LOGIC ---
-             for &sample in &processed_chunk {
-                 if ring_buf.len() >= RING_BUFFER_SIZE {
-                     ring_buf.pop_front();
+             // Flush the pre-roll *before* adding this chunk, otherwise it would be sent twice
+             if recording_now && !self.was_rec {
+                 // New session: pick up the latest VAD settings and forget the old silence count
+                 let vad_settings = self
+                     .shared
+                     .calibration
+                     .lock()
+                     .vad_for(&self.device_id, &self.shared.vad_settings.lock());
+                 self.vad.configure(&vad_settings);
+                 self.wake_session = std::mem::take(&mut self.wake_pending);
+                 let start = self
+                     .ring_buf
+                     .len()
+                     .saturating_sub(PRE_ROLL_SECONDS * SAMPLE_RATE as usize);
+                 let history: Vec<f32> = self.ring_buf.range(start..).copied().collect();
+                 if !history.is_empty() {
+                     self.tx.push(SensitiveAudio::new(history));
                  }
-                 ring_buf.push_back(sample);
              }
 
-             if recording_now && !*was_rec {
-                 let history: Vec<f32> = ring_buf.iter().cloned().collect();
-                 let _ = tx.try_send(SensitiveAudio::new(history));
+             let ring_secs = (*self.shared.capture_secs.lock()).min(MAX_CAPTURE_SECONDS) as usize;
+             let ring_size = ring_secs.max(PRE_ROLL_SECONDS) * SAMPLE_RATE as usize;
+             self.ring_buf.extend(processed_chunk.iter().copied());
+             let excess = self.ring_buf.len().saturating_sub(ring_size);
+             self.ring_buf.drain(..excess);
+
+             // --- RETROACTIVE CAPTURE ---
+             let capture = self.shared.capture_request.lock().take();
+             if let Some(seconds) = capture {
+                 if recording_now {
+                     println!("[DEBUG] Retroactive capture ignored: already recording.");
+                 } else {
+                     self.send_capture(seconds as usize * SAMPLE_RATE as usize);
+                 }
              }
 
              if recording_now {
-                 let _ = tx.try_send(SensitiveAudio::new(processed_chunk));
+                 // --- HANDS-FREE AUTO-STOP ---
+                 // Only the ring buffer flush above is pre-roll; the VAD sees live audio only.
+                 let auto_stop = self.shared.vad_settings.lock().auto_stop || self.wake_session;
+                 if auto_stop && self.vad.push(&processed_chunk) {
+                     *self.shared.auto_stop.lock() = true;
+                 }
+                 self.tx.push(SensitiveAudio::new(processed_chunk));
+             } else {
+                 // --- WAKE WORD ---
+                 let detection = {
+                     // Locked, not cloned: the phrase would be an allocation per block
+                     let wake_settings = self.shared.wake_word.lock();
+                     if self.was_rec {
+                         self.wake_session = false;
+                         self.wake.session_finished(&wake_settings);
+                     }
+                     let detection = if self.wake_pending {
+                         None
+                     } else {
+                         self.wake.push(&wake_settings, &processed_chunk)
+                     };
+                     if let Some(detection) = &detection {
+                         println!(
+                             "[DEBUG] Wake word \"{}\" heard (score {:.2})",
+                             wake_settings.phrase, detection.score
+                         );
+                     }
+                     detection
+                 };
+                 if let Some(detection) = detection {
+                     // The pre-roll becomes the session start: keep only what followed the phrase
+                     self.trim_ring_buffer(detection.keep_samples);
+                     self.wake_pending = true;
+                     *self.shared.wake_request.lock() = true;
+                 }
              }
 
-             *was_rec = recording_now;
+             self.was_rec = recording_now;
+        }
+    }
+
+    /// Drops all but the newest `keep` samples of the ring buffer.
+    fn trim_ring_buffer(&mut self, keep: usize) {
+        let excess = self.ring_buf.len().saturating_sub(keep);
+        // Security: overwrite before releasing the slots
+        self.ring_buf.range_mut(..excess).for_each(|s| *s = 0.0);
+        self.ring_buf.drain(..excess);
+    }
+
+    /// Hands the newest `samples` of the ring buffer over as a finished session and
+    /// clears it, so the same audio does not come back as the next recording's pre-roll.
+    fn send_capture(&mut self, samples: usize) {
+        let start = self.ring_buf.len().saturating_sub(samples);
+        let snapshot: Vec<f32> = self.ring_buf.range(start..).copied().collect();
+        // Security: overwrite before releasing the slots
+        self.ring_buf.iter_mut().for_each(|s| *s = 0.0);
+        self.ring_buf.clear();
+
+        println!("[DEBUG] Retroactive capture: {} samples", snapshot.len());
+        if !snapshot.is_empty()
+            && self
+                .shared
+                .capture_tx
+                .try_send(SensitiveAudio::new(snapshot))
+                .is_err()
+        {
+            println!("[WARNING] Retroactive capture dropped: earlier captures are still being transcribed.");
+            *self.shared.capture_dropped.lock() = true;
         }
     }
 }
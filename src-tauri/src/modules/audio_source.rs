@@ -0,0 +1,514 @@
+use crate::modules::audio::{AudioEngine, AudioPipeline, StreamShared};
+use crate::modules::devices::DeviceInfo;
+use crate::modules::handoff::AudioSender;
+use crate::modules::loopback::{self, MonitorCapture, MONITOR_SAMPLE_RATE};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// File/stdin sources push 100 ms blocks into the pipeline
+const BLOCK_MS: usize = 100;
+// A live stream that delivers no blocks for this long is treated as lost
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+// Wait between attempts while no device can be opened
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+// While on a fallback device, how often to check whether the selected one is back
+const PREFERRED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where the always-on stream gets its samples from. Every source feeds the same
+/// downmix -> resample -> ring buffer pipeline and ends up on the audio hand-off queue.
+pub trait AudioSource: Send {
+    fn name(&self) -> String;
+    fn start(
+        self: Box<Self>,
+        tx: AudioSender,
+        shared: StreamShared,
+    ) -> Result<SourceHandle>;
+}
+
+/// Keeps a running source alive. Dropping it stops the source.
+pub enum SourceHandle {
+    Cpal {
+        stream: cpal::Stream,
+        /// The device that was actually opened.
+        device: DeviceInfo,
+        /// System-audio monitor feeding the mic + system mix, if enabled.
+        mix_feed: Option<MonitorCapture>,
+    },
+    /// System audio recorded as the input (see loopback.rs).
+    Monitor {
+        capture: MonitorCapture,
+        device: DeviceInfo,
+    },
+    Thread {
+        stop: Arc<AtomicBool>,
+        join: Option<JoinHandle<()>>,
+    },
+}
+
+impl SourceHandle {
+    fn spawn(name: &str, f: impl FnOnce(Arc<AtomicBool>) + Send + 'static) -> Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+        let join = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || f(stop_clone))?;
+        Ok(SourceHandle::Thread {
+            stop,
+            join: Some(join),
+        })
+    }
+
+    /// The input device behind a live microphone stream.
+    pub fn device(&self) -> Option<&DeviceInfo> {
+        match self {
+            SourceHandle::Cpal { device, .. } | SourceHandle::Monitor { device, .. } => {
+                Some(device)
+            }
+            SourceHandle::Thread { .. } => None,
+        }
+    }
+
+    /// Blocks until a finite source (e.g. a WAV file) has delivered all of its audio.
+    /// Returns immediately for live sources.
+    pub fn wait(mut self) {
+        if let SourceHandle::Thread { join, .. } = &mut self {
+            if let Some(j) = join.take() {
+                let _ = j.join();
+            }
+        }
+    }
+}
+
+impl Drop for SourceHandle {
+    fn drop(&mut self) {
+        if let SourceHandle::Thread { stop, .. } = self {
+            // Don't join: a stdin reader may be blocked in read()
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// --- cpal (microphone) ---
+
+pub struct CpalSource {
+    /// Device id (or a name from an older config), `None` = system default.
+    pub device_name: Option<String>,
+}
+
+impl AudioSource for CpalSource {
+    fn name(&self) -> String {
+        format!("cpal ({})", self.device_name.as_deref().unwrap_or("default"))
+    }
+
+    fn start(
+        self: Box<Self>,
+        tx: AudioSender,
+        shared: StreamShared,
+    ) -> Result<SourceHandle> {
+        use cpal::traits::StreamTrait;
+        // System audio alone: recorded straight from its monitor, no mix
+        let id = self.device_name.as_deref().unwrap_or_default();
+        if let Some(monitor) = loopback::monitor_name(id) {
+            // parec would just exit, so fail here and let the supervisor fall back
+            if !AudioEngine::input_device_available(id) {
+                return Err(anyhow!("System audio source {} not found", monitor));
+            }
+            let mut pipeline = AudioPipeline::new(
+                tx,
+                shared.clone(),
+                id.to_string(),
+                1,
+                MONITOR_SAMPLE_RATE,
+            )?;
+            let health = shared.health.clone();
+            let capture = MonitorCapture::start(
+                monitor,
+                move |data| pipeline.process(data),
+                // Like a cpal stream error: let the supervisor rebuild
+                move || health.failed.store(true, Ordering::Relaxed),
+            )?;
+            return Ok(SourceHandle::Monitor {
+                capture,
+                device: loopback::loopback_device(monitor),
+            });
+        }
+
+        let mix = shared.mix_settings.lock().clone();
+        let bus = shared.mix_bus.clone();
+        let (stream, device) = AudioEngine::start_stream(tx, shared, self.device_name)?;
+        stream.play()?;
+
+        let mix_feed = if mix.enabled {
+            match loopback::start_mix_feed(&bus, mix.monitor.as_deref()) {
+                Ok(feed) => Some(feed),
+                Err(e) => {
+                    // Keep recording the microphone alone
+                    println!("[WARNING] System audio mix unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Ok(SourceHandle::Cpal {
+            stream,
+            device,
+            mix_feed,
+        })
+    }
+}
+
+// --- WAV file (hound) ---
+
+pub struct WavFileSource {
+    pub path: PathBuf,
+    /// Pace delivery like a live microphone. Off = as fast as the pipeline takes it.
+    pub realtime: bool,
+}
+
+impl WavFileSource {
+    fn read_samples(&self) -> Result<(hound::WavSpec, Vec<f32>)> {
+        let mut reader = hound::WavReader::open(&self.path)?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|v| v as f32 / scale))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        Ok((spec, samples))
+    }
+}
+
+impl AudioSource for WavFileSource {
+    fn name(&self) -> String {
+        format!("wav ({})", self.path.display())
+    }
+
+    fn start(
+        self: Box<Self>,
+        tx: AudioSender,
+        shared: StreamShared,
+    ) -> Result<SourceHandle> {
+        let (spec, samples) = self.read_samples()?;
+        let channels = spec.channels as usize;
+        let mut pipeline =
+            AudioPipeline::new(tx, shared, self.name(), channels, spec.sample_rate)?;
+        let block = spec.sample_rate as usize * BLOCK_MS / 1000 * channels;
+        let realtime = self.realtime;
+
+        println!(
+            "[DEBUG] WAV source: {} Hz, {} ch, {} samples",
+            spec.sample_rate,
+            channels,
+            samples.len()
+        );
+
+        SourceHandle::spawn("vibeflow-wav-source", move |stop| {
+            for chunk in samples.chunks(block) {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                pipeline.process(chunk);
+                if realtime {
+                    std::thread::sleep(Duration::from_millis(BLOCK_MS as u64));
+                }
+            }
+            println!("[DEBUG] WAV source finished.");
+        })
+    }
+}
+
+// --- Raw PCM on stdin ---
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PcmFormat {
+    S16Le,
+    F32Le,
+}
+
+pub struct StdinPcmSource {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub format: PcmFormat,
+}
+
+impl AudioSource for StdinPcmSource {
+    fn name(&self) -> String {
+        format!("stdin ({} Hz, {} ch, {:?})", self.sample_rate, self.channels, self.format)
+    }
+
+    fn start(
+        self: Box<Self>,
+        tx: AudioSender,
+        shared: StreamShared,
+    ) -> Result<SourceHandle> {
+        let mut pipeline =
+            AudioPipeline::new(tx, shared, self.name(), self.channels, self.sample_rate)?;
+        let bytes_per_sample = match self.format {
+            PcmFormat::S16Le => 2,
+            PcmFormat::F32Le => 4,
+        };
+        let frame_bytes = bytes_per_sample * self.channels;
+        let block_bytes = self.sample_rate as usize * BLOCK_MS / 1000 * frame_bytes;
+        let format = self.format;
+
+        SourceHandle::spawn("vibeflow-stdin-source", move |stop| {
+            let mut stdin = std::io::stdin().lock();
+            let mut buf = vec![0u8; block_bytes];
+            let mut filled = 0;
+
+            while !stop.load(Ordering::Relaxed) {
+                let n = match stdin.read(&mut buf[filled..]) {
+                    Ok(0) => break, // EOF
+                    Ok(n) => n,
+                    Err(e) => {
+                        println!("[ERROR] stdin source read failed: {}", e);
+                        break;
+                    }
+                };
+                filled += n;
+
+                // Only hand whole frames to the pipeline, keep the remainder
+                let usable = filled - filled % frame_bytes;
+                if usable == 0 {
+                    continue;
+                }
+                let samples = decode_pcm(&buf[..usable], format);
+                pipeline.process(&samples);
+                buf.copy_within(usable..filled, 0);
+                filled -= usable;
+            }
+            println!("[DEBUG] stdin source finished.");
+        })
+    }
+}
+
+fn decode_pcm(bytes: &[u8], format: PcmFormat) -> Vec<f32> {
+    match format {
+        PcmFormat::S16Le => bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        PcmFormat::F32Le => bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    }
+}
+
+/// Picks the source from `VIBEFLOW_AUDIO_SOURCE`, falling back to the microphone:
+/// - `cpal` (default)
+/// - `wav:<path>`
+/// - `stdin:<rate>:<channels>[:s16|:f32]`
+pub fn source_from_env(device_name: Option<String>) -> Result<Box<dyn AudioSource>> {
+    match std::env::var("VIBEFLOW_AUDIO_SOURCE") {
+        Ok(spec) => parse_source(&spec, device_name),
+        Err(_) => Ok(Box::new(CpalSource { device_name })),
+    }
+}
+
+pub fn parse_source(spec: &str, device_name: Option<String>) -> Result<Box<dyn AudioSource>> {
+    let mut parts = spec.splitn(2, ':');
+    let kind = parts.next().unwrap_or_default();
+    let rest = parts.next().unwrap_or_default();
+
+    match kind {
+        "cpal" => Ok(Box::new(CpalSource { device_name })),
+        "wav" if !rest.is_empty() => Ok(Box::new(WavFileSource {
+            path: PathBuf::from(rest),
+            realtime: true,
+        })),
+        "stdin" => {
+            let fields: Vec<&str> = rest.split(':').collect();
+            let sample_rate = fields
+                .first()
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| anyhow!("stdin source needs a sample rate, e.g. stdin:16000:1"))?;
+            let channels = fields
+                .get(1)
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(1)
+                .max(1);
+            let format = match fields.get(2).copied() {
+                Some("f32") => PcmFormat::F32Le,
+                _ => PcmFormat::S16Le,
+            };
+            Ok(Box::new(StdinPcmSource {
+                sample_rate,
+                channels,
+                format,
+            }))
+        }
+        _ => Err(anyhow!("Unknown audio source \"{}\"", spec)),
+    }
+}
+
+/// What the UI shows as the active input. Emitted as `"audio_device"`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DeviceStatus {
+    /// Id of the device the stream is running on, `None` while deaf.
+    pub active: Option<String>,
+    /// Display name of the active device.
+    pub active_name: Option<String>,
+    /// The device selected in settings (`None` = system default).
+    pub requested: Option<String>,
+    /// Running on something other than the selected device (it is missing or broken).
+    pub fallback: bool,
+}
+
+/// Keeps the always-on stream alive: rebuilds it when the selected device changes,
+/// when cpal reports an error or when callbacks stop arriving, and moves back to the
+/// selected device once it reappears. Call `tick` from a polling loop.
+pub struct StreamSupervisor {
+    tx: AudioSender,
+    shared: StreamShared,
+    selected_device: Arc<Mutex<Option<String>>>,
+    handle: Option<SourceHandle>,
+    status: DeviceStatus,
+    // Mix on/off and monitor the current stream was opened with
+    mix: (bool, Option<String>),
+    last_blocks: u64,
+    last_progress: Instant,
+    next_attempt: Instant,
+    next_preferred_poll: Instant,
+}
+
+impl StreamSupervisor {
+    pub fn new(
+        tx: AudioSender,
+        shared: StreamShared,
+        selected_device: Arc<Mutex<Option<String>>>,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            tx,
+            shared,
+            selected_device,
+            handle: None,
+            status: DeviceStatus::default(),
+            mix: (false, None),
+            last_blocks: 0,
+            last_progress: now,
+            next_attempt: now,
+            next_preferred_poll: now,
+        }
+    }
+
+    /// Checks the stream and rebuilds it if needed.
+    /// Returns the new status whenever it changed.
+    pub fn tick(&mut self) -> Option<DeviceStatus> {
+        // File/stdin sources run once and end on their own
+        if matches!(self.handle, Some(SourceHandle::Thread { .. })) {
+            return None;
+        }
+
+        let requested = self.selected_device.lock().clone();
+        let now = Instant::now();
+
+        let reason = if self.handle.is_none() {
+            (now >= self.next_attempt).then_some("no active stream")
+        } else if requested != self.status.requested {
+            Some("input device changed")
+        } else if self.mix_key() != self.mix {
+            Some("system audio mix changed")
+        } else if self.shared.health.failed.load(Ordering::Relaxed) {
+            Some("stream error")
+        } else if self.is_stalled(now) {
+            Some("stream stalled")
+        } else if self.status.fallback && now >= self.next_preferred_poll {
+            self.next_preferred_poll = now + PREFERRED_POLL_INTERVAL;
+            requested
+                .as_deref()
+                .filter(|name| AudioEngine::input_device_available(name))
+                .map(|_| "selected device is back")
+        } else {
+            None
+        };
+        let reason = reason?;
+
+        println!("[DEBUG] Rebuilding audio stream: {}", reason);
+        let previous = self.status.clone();
+        self.rebuild(requested);
+        (self.status != previous).then(|| self.status.clone())
+    }
+
+    fn is_stalled(&mut self, now: Instant) -> bool {
+        let blocks = self.shared.health.blocks.load(Ordering::Relaxed);
+        if blocks != self.last_blocks {
+            self.last_blocks = blocks;
+            self.last_progress = now;
+            return false;
+        }
+        now.duration_since(self.last_progress) > STALL_TIMEOUT
+    }
+
+    fn mix_key(&self) -> (bool, Option<String>) {
+        let mix = self.shared.mix_settings.lock();
+        (mix.enabled, mix.monitor.clone())
+    }
+
+    fn rebuild(&mut self, requested: Option<String>) {
+        // Release the old device first, some ALSA devices can only be opened once
+        self.handle = None;
+        self.shared.health.failed.store(false, Ordering::Relaxed);
+        self.mix = self.mix_key();
+
+        let mut result = self.start(requested.clone());
+        if result.is_err() && requested.is_some() {
+            // Selected device is gone: keep the preference, run on the default meanwhile
+            println!("[WARNING] Selected input device unavailable, falling back to default.");
+            result = self.start(None);
+        }
+
+        let now = Instant::now();
+        self.last_blocks = self.shared.health.blocks.load(Ordering::Relaxed);
+        self.last_progress = now;
+        self.next_preferred_poll = now + PREFERRED_POLL_INTERVAL;
+
+        match result {
+            Ok(handle) => {
+                let device = handle.device();
+                // `requested` may still be a pre-id name, so compare via the device
+                let fallback = match (&requested, device) {
+                    (Some(r), Some(d)) => !d.matches(r),
+                    _ => false,
+                };
+                self.status = DeviceStatus {
+                    active: device.map(|d| d.id.clone()),
+                    active_name: device.map(|d| d.display_name.clone()),
+                    requested,
+                    fallback,
+                };
+                self.handle = Some(handle);
+                println!("[DEBUG] Audio stream active: {:?}", self.status);
+            }
+            Err(e) => {
+                println!("[ERROR] Failed to start audio stream: {}. Retrying...", e);
+                self.status = DeviceStatus {
+                    requested,
+                    ..DeviceStatus::default()
+                };
+                self.next_attempt = now + RETRY_INTERVAL;
+            }
+        }
+    }
+
+    fn start(&self, device_name: Option<String>) -> Result<SourceHandle> {
+        // Microphone by default; VIBEFLOW_AUDIO_SOURCE can swap in a WAV file or stdin PCM
+        let source = source_from_env(device_name)?;
+        println!("[DEBUG] Audio source: {}", source.name());
+        source.start(self.tx.clone(), self.shared.clone())
+    }
+}
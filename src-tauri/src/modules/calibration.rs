@@ -0,0 +1,293 @@
+use crate::modules::devices::DeviceInfo;
+use crate::modules::handoff::{audio_queue, AudioSender};
+use crate::modules::levels::{self, GainSettings};
+use crate::modules::vad::VadSettings;
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Calibration wizard: a few seconds of room noise, then a few seconds of speech,
+// measured on the running stream of the selected device at 16 kHz (channel
+// policy and denoise as configured, before the AGC). The result is a per-device profile on top of the
+// global gain and VAD settings.
+
+const SAMPLE_RATE: usize = 16000;
+// 10 ms blocks, the unit the level percentiles are taken over
+const BLOCK_SAMPLES: usize = SAMPLE_RATE / 100;
+pub const MAX_PHASE_SECONDS: u32 = 15;
+// Samples at or above this count as clipped
+const CLIP_LEVEL: f32 = 0.99;
+const CLIP_RATIO_ISSUE: f32 = 0.001;
+// Speech must stand this far above the noise floor to count as heard at all
+const MIN_SPEECH_MARGIN_DB: f32 = 6.0;
+const LOW_SNR_DB: f32 = 15.0;
+const TOO_QUIET_DBFS: f32 = -45.0;
+// The AGC may lift speech this much above the target before the limiter steps in
+const GAIN_HEADROOM_DB: f32 = 6.0;
+const MAX_SUGGESTED_GAIN_DB: f32 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalibrationPhase {
+    Silence,
+    Speech,
+}
+
+impl CalibrationPhase {
+    pub fn default_seconds(self) -> u32 {
+        match self {
+            CalibrationPhase::Silence => 3,
+            CalibrationPhase::Speech => 5,
+        }
+    }
+}
+
+/// Levels of one phase, over 10 ms blocks of the 16 kHz stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseMeasurement {
+    pub phase: CalibrationPhase,
+    pub duration_ms: u64,
+    pub rms_dbfs: f32,
+    /// Quietest tenth of the blocks lies below this.
+    pub p10_dbfs: f32,
+    pub p50_dbfs: f32,
+    /// Loud parts (stressed syllables) for speech.
+    pub p90_dbfs: f32,
+    pub peak_dbfs: f32,
+    pub clipped_ratio: f32,
+}
+
+/// Accumulates a phase block by block, so no audio is kept around.
+pub struct PhaseMeter {
+    phase: CalibrationPhase,
+    block: Vec<f32>,
+    blocks_dbfs: Vec<f32>,
+    sum_squares: f64,
+    samples: u64,
+    peak: f32,
+    clipped: u64,
+}
+
+impl PhaseMeter {
+    pub fn new(phase: CalibrationPhase) -> Self {
+        Self {
+            phase,
+            block: Vec::with_capacity(BLOCK_SAMPLES),
+            blocks_dbfs: Vec::new(),
+            sum_squares: 0.0,
+            samples: 0,
+            peak: 0.0,
+            clipped: 0,
+        }
+    }
+
+    /// Feeds 16 kHz mono samples.
+    pub fn push(&mut self, samples: &[f32]) {
+        for &s in samples {
+            self.peak = self.peak.max(s.abs());
+            self.sum_squares += (s * s) as f64;
+            if s.abs() >= CLIP_LEVEL {
+                self.clipped += 1;
+            }
+            self.block.push(s);
+            if self.block.len() == BLOCK_SAMPLES {
+                self.blocks_dbfs.push(levels::to_dbfs(levels::rms(&self.block)));
+                self.block.clear();
+            }
+        }
+        self.samples += samples.len() as u64;
+    }
+
+    pub fn finish(mut self) -> Result<PhaseMeasurement> {
+        if self.blocks_dbfs.is_empty() {
+            return Err(anyhow!("No audio was recorded"));
+        }
+        self.blocks_dbfs.sort_by(f32::total_cmp);
+        let percentile = |p: usize| self.blocks_dbfs[(self.blocks_dbfs.len() - 1) * p / 100];
+
+        Ok(PhaseMeasurement {
+            phase: self.phase,
+            duration_ms: self.samples * 1000 / SAMPLE_RATE as u64,
+            rms_dbfs: levels::to_dbfs((self.sum_squares / self.samples as f64).sqrt() as f32),
+            p10_dbfs: percentile(10),
+            p50_dbfs: percentile(50),
+            p90_dbfs: percentile(90),
+            peak_dbfs: levels::to_dbfs(self.peak),
+            clipped_ratio: self.clipped as f32 / self.samples as f32,
+        })
+    }
+}
+
+/// Calibrated settings for one device, persisted under `"calibration"` in config.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub noise_floor_dbfs: f32,
+    pub speech_dbfs: f32,
+    /// Replaces `vad.aggressiveness` on this device.
+    pub vad_aggressiveness: u8,
+    /// Replace `gain.max_gain_db` / `gain.quiet_dbfs` on this device.
+    pub max_gain_db: f32,
+    pub quiet_dbfs: f32,
+}
+
+/// Profiles keyed by device id, like the channel and denoise settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalibrationProfiles {
+    pub devices: HashMap<String, DeviceProfile>,
+}
+
+impl CalibrationProfiles {
+    pub fn gain_for(&self, device_id: &str, base: &GainSettings) -> GainSettings {
+        match self.devices.get(device_id) {
+            Some(p) => GainSettings {
+                max_gain_db: p.max_gain_db,
+                quiet_dbfs: p.quiet_dbfs,
+                ..base.clone()
+            },
+            None => base.clone(),
+        }
+    }
+
+    pub fn vad_for(&self, device_id: &str, base: &VadSettings) -> VadSettings {
+        match self.devices.get(device_id) {
+            Some(p) => VadSettings {
+                aggressiveness: p.vad_aggressiveness,
+                ..base.clone()
+            },
+            None => base.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalibrationIssue {
+    /// Speech barely rises above the noise: muted mic or wrong device.
+    NoSpeech,
+    Clipping,
+    TooQuiet,
+    LowSnr,
+}
+
+/// What the wizard shows at the end.
+#[derive(Debug, Clone, Serialize)]
+pub struct CalibrationResult {
+    /// Id of the device the profile is stored under.
+    pub device: String,
+    pub noise_floor_dbfs: f32,
+    pub speech_dbfs: f32,
+    pub snr_db: f32,
+    pub issues: Vec<CalibrationIssue>,
+    pub profile: DeviceProfile,
+}
+
+/// Turns the two measurements into suggested settings.
+pub fn suggest(
+    device: &str,
+    silence: &PhaseMeasurement,
+    speech: &PhaseMeasurement,
+    gain: &GainSettings,
+) -> CalibrationResult {
+    let noise_floor = silence.p50_dbfs;
+    let speech_level = speech.p90_dbfs;
+    let snr = speech_level - noise_floor;
+
+    let mut issues = Vec::new();
+    if snr < MIN_SPEECH_MARGIN_DB {
+        issues.push(CalibrationIssue::NoSpeech);
+    } else if snr < LOW_SNR_DB {
+        issues.push(CalibrationIssue::LowSnr);
+    }
+    if speech.clipped_ratio > CLIP_RATIO_ISSUE {
+        issues.push(CalibrationIssue::Clipping);
+    }
+    if speech_level < TOO_QUIET_DBFS {
+        issues.push(CalibrationIssue::TooQuiet);
+    }
+
+    // Quiet room: a gentle VAD keeps soft word endings; noisy room: a strict one
+    let vad_aggressiveness = if snr >= 30.0 && noise_floor < -60.0 {
+        1
+    } else if snr >= 20.0 {
+        2
+    } else {
+        3
+    };
+    // Enough boost to bring this speaker to the target, no more
+    let max_gain_db = (gain.target_dbfs - speech_level + GAIN_HEADROOM_DB)
+        .clamp(0.0, MAX_SUGGESTED_GAIN_DB)
+        .round();
+    // Warn when a recording's loudest part is no louder than halfway to speech
+    let quiet_dbfs = ((noise_floor + speech_level) / 2.0).clamp(-70.0, -30.0).round();
+
+    CalibrationResult {
+        device: device.to_string(),
+        noise_floor_dbfs: noise_floor,
+        speech_dbfs: speech_level,
+        snr_db: snr,
+        issues,
+        profile: DeviceProfile {
+            noise_floor_dbfs: noise_floor,
+            speech_dbfs: speech_level,
+            vad_aggressiveness,
+            max_gain_db,
+            quiet_dbfs,
+        },
+    }
+}
+
+/// The wizard's progress between commands.
+#[derive(Debug, Default)]
+pub struct CalibrationRun {
+    pub device: Option<DeviceInfo>,
+    pub silence: Option<PhaseMeasurement>,
+    pub speech: Option<PhaseMeasurement>,
+}
+
+// Removes the calibration tap however the step ends
+struct TapGuard<'a>(&'a Mutex<Option<AudioSender>>);
+
+impl Drop for TapGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.lock() = None;
+    }
+}
+
+/// Records one phase from the always-on stream, which copies its 16 kHz audio
+/// into `tap` meanwhile (no second stream on a device it already holds).
+/// Blocks for `seconds`; fails while another step holds the tap.
+pub fn record_phase(
+    phase: CalibrationPhase,
+    seconds: u32,
+    tap: &Mutex<Option<AudioSender>>,
+) -> Result<PhaseMeasurement> {
+    let (tx, mut rx) = audio_queue(SAMPLE_RATE * MAX_PHASE_SECONDS as usize * 2);
+    {
+        // A second step would take the tap over and leave the first measuring nothing
+        let mut slot = tap.lock();
+        if slot.is_some() {
+            return Err(anyhow!("A calibration step is already running"));
+        }
+        *slot = Some(tx);
+    }
+    let installed = TapGuard(tap);
+    println!("[DEBUG] Calibration: measuring {:?} for {} s", phase, seconds);
+
+    let mut meter = PhaseMeter::new(phase);
+    let deadline = std::time::Instant::now() + Duration::from_secs(seconds as u64);
+    while std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+        while let Some(audio) = rx.try_recv() {
+            meter.push(audio.as_slice());
+        }
+    }
+    drop(installed);
+    while let Some(audio) = rx.try_recv() {
+        meter.push(audio.as_slice());
+    }
+
+    meter.finish()
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Max-energy: smoothing of the per-channel energy and the margin another
+// channel needs before we switch to it (avoids flapping between mics)
+const ENERGY_SMOOTHING: f32 = 0.9;
+const SWITCH_RATIO: f32 = 2.0; // ~3 dB
+// Delay-and-sum: largest inter-channel delay we search for, in ms
+// (1 ms ~ 34 cm of path difference, plenty for array and headset mics)
+const MAX_DELAY_MS: f32 = 1.0;
+const CORRELATION_SMOOTHING: f32 = 0.95;
+// Blocks quieter than this carry no usable delay information
+const DELAY_ESTIMATE_MIN_RMS: f32 = 0.003;
+
+/// How a multichannel input is turned into the mono stream.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ChannelPolicy {
+    /// Mean of all channels (the old behaviour).
+    Average,
+    /// A single input, 0-based. Falls back to the last channel if out of range.
+    Channel { index: usize },
+    /// Follow whichever channel currently carries the most energy.
+    MaxEnergy,
+    /// Time-align all channels to the first one, then average.
+    DelayAndSum,
+}
+
+/// Per-device channel policy, persisted as `"channels"` in config.json.
+/// Keys are device ids, like the denoise settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSettings {
+    pub default_policy: ChannelPolicy,
+    pub devices: HashMap<String, ChannelPolicy>,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        Self {
+            default_policy: ChannelPolicy::Average,
+            devices: HashMap::new(),
+        }
+    }
+}
+
+impl ChannelSettings {
+    pub fn policy_for(&self, device_id: &str) -> ChannelPolicy {
+        self.devices
+            .get(device_id)
+            .copied()
+            .unwrap_or(self.default_policy)
+    }
+}
+
+/// Stateful interleaved -> mono conversion for one stream.
+pub struct ChannelMixer {
+    channels: usize,
+    // Max-energy
+    energies: Vec<f32>,
+    selected: usize,
+    // Delay-and-sum: last 2 * max_lag samples per channel, and accumulated
+    // cross-correlation against channel 0 for every lag in -max_lag..=max_lag
+    max_lag: usize,
+    history: Vec<Vec<f32>>,
+    correlations: Vec<Vec<f32>>,
+    delays: Vec<isize>,
+}
+
+impl ChannelMixer {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        let max_lag = ((sample_rate as f32 * MAX_DELAY_MS / 1000.0) as usize).max(1);
+        Self {
+            channels,
+            energies: vec![0.0; channels],
+            selected: 0,
+            max_lag,
+            history: vec![vec![0.0; 2 * max_lag]; channels],
+            correlations: vec![vec![0.0; 2 * max_lag + 1]; channels],
+            delays: vec![0; channels],
+        }
+    }
+
+    /// Converts one interleaved block to mono using `policy`.
+    pub fn mix(&mut self, data: &[f32], policy: ChannelPolicy) -> Vec<f32> {
+        let frames = data.len() / self.channels;
+        if self.channels == 1 {
+            return data.to_vec();
+        }
+
+        match policy {
+            ChannelPolicy::Average => data
+                .chunks_exact(self.channels)
+                .map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+                .collect(),
+            ChannelPolicy::Channel { index } => {
+                let index = index.min(self.channels - 1);
+                data.chunks_exact(self.channels).map(|frame| frame[index]).collect()
+            }
+            ChannelPolicy::MaxEnergy => self.mix_max_energy(data, frames),
+            ChannelPolicy::DelayAndSum => self.mix_delay_and_sum(data, frames),
+        }
+    }
+
+    fn mix_max_energy(&mut self, data: &[f32], frames: usize) -> Vec<f32> {
+        if frames == 0 {
+            return Vec::new();
+        }
+        for (c, energy) in self.energies.iter_mut().enumerate() {
+            let block: f32 = data
+                .iter()
+                .skip(c)
+                .step_by(self.channels)
+                .map(|x| x * x)
+                .sum::<f32>()
+                / frames as f32;
+            *energy = ENERGY_SMOOTHING * *energy + (1.0 - ENERGY_SMOOTHING) * block;
+        }
+
+        let (loudest, &loudest_energy) = self
+            .energies
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap_or((0, &0.0));
+        let previous = self.selected;
+        if loudest != previous && loudest_energy > self.energies[previous] * SWITCH_RATIO {
+            self.selected = loudest;
+        }
+
+        // Crossfade over the block when switching, so the change doesn't click
+        let channels = self.channels;
+        data.chunks_exact(channels)
+            .enumerate()
+            .map(|(i, frame)| {
+                if previous == self.selected {
+                    frame[previous]
+                } else {
+                    let t = (i + 1) as f32 / frames as f32;
+                    frame[previous] * (1.0 - t) + frame[self.selected] * t
+                }
+            })
+            .collect()
+    }
+
+    fn mix_delay_and_sum(&mut self, data: &[f32], frames: usize) -> Vec<f32> {
+        let lag = self.max_lag;
+        // Per channel: history (2 * lag) followed by this block
+        let extended: Vec<Vec<f32>> = (0..self.channels)
+            .map(|c| {
+                let mut ext = self.history[c].clone();
+                ext.extend(data.iter().skip(c).step_by(self.channels).take(frames));
+                ext
+            })
+            .collect();
+
+        let reference_rms = (extended[0][lag..lag + frames]
+            .iter()
+            .map(|x| x * x)
+            .sum::<f32>()
+            / frames.max(1) as f32)
+            .sqrt();
+        if frames > 0 && reference_rms > DELAY_ESTIMATE_MIN_RMS {
+            self.update_delays(&extended, frames);
+        }
+
+        // y[n] = mean_c x_c[n - lag + d_c], i.e. the output runs `lag` samples behind
+        let output = (0..frames)
+            .map(|n| {
+                extended
+                    .iter()
+                    .zip(&self.delays)
+                    .map(|(ext, &d)| ext[((lag + n) as isize + d) as usize])
+                    .sum::<f32>()
+                    / self.channels as f32
+            })
+            .collect();
+
+        for (c, ext) in extended.into_iter().enumerate() {
+            let keep = ext.len() - 2 * lag;
+            self.history[c].copy_from_slice(&ext[keep..]);
+        }
+        output
+    }
+
+    fn update_delays(&mut self, extended: &[Vec<f32>], frames: usize) {
+        let lag = self.max_lag as isize;
+        for c in 1..self.channels {
+            let correlations = &mut self.correlations[c];
+            for (k, acc) in correlations.iter_mut().enumerate() {
+                let d = k as isize - lag;
+                let r: f32 = (0..frames as isize)
+                    .map(|n| {
+                        extended[0][(lag + n) as usize] * extended[c][(lag + n + d) as usize]
+                    })
+                    .sum();
+                *acc = CORRELATION_SMOOTHING * *acc + r;
+            }
+            let best = correlations
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(k, _)| k as isize - lag)
+                .unwrap_or(0);
+            self.delays[c] = best;
+        }
+    }
+}
@@ -1,23 +1,83 @@
-use crate::modules::audio::AudioEngine;
+use crate::modules::archive::{ArchiveSettings, SessionMeta};
+use crate::modules::audio::{AudioEngine, MAX_CAPTURE_SECONDS};
+use crate::modules::audio_source::DeviceStatus;
+use crate::modules::calibration::{
+    self, CalibrationPhase, CalibrationProfiles, CalibrationResult, PhaseMeasurement,
+};
+use crate::modules::channels::{ChannelPolicy, ChannelSettings};
+use crate::modules::decoding::DecodingSettings;
+use crate::modules::denoise::DenoiseSettings;
+use crate::modules::devices::DeviceInfo;
+use crate::modules::dictionary::{Dictionary, DICTIONARY_FILE};
+use crate::modules::handoff::HandoffStats;
+use crate::modules::hotkeys::HotkeyBehavior;
+use crate::modules::language::{self, LanguageSettings};
+use crate::modules::levels::{GainSettings, LevelStats};
+use crate::modules::llm::AppMode;
+use crate::modules::loopback::{self, MixSettings};
+use crate::modules::model_manager::{ModelSettings, ModelStatus};
 use crate::modules::state::AppState;
+use crate::modules::transcript::TranscriptSettings;
+use crate::modules::vad::VadSettings;
+use crate::modules::wakeword::{self, WakeWordSettings};
 use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_global_shortcut::{Code, Modifiers};
 
+/// Input devices, default first. Select with `set_audio_device(id)`; per-device
+/// settings (channel policy, denoise, calibration) use the same id.
 #[tauri::command]
-pub fn get_audio_devices() -> Result<Vec<String>, String> {
-    AudioEngine::list_input_devices().map_err(|e| e.to_string())
+pub fn get_audio_devices() -> Vec<DeviceInfo> {
+    AudioEngine::list_devices()
 }
 
+#[tauri::command]
+pub fn get_channel_settings(state: State<'_, AppState>) -> ChannelSettings {
+    state.channel_settings.lock().clone()
+}
+
+/// Set the channel policy for one device (id), or the default when `device` is None.
+/// The running stream picks the change up on its next callback.
+#[tauri::command]
+pub fn set_channel_policy(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    device: Option<String>,
+    policy: ChannelPolicy,
+) -> Result<(), String> {
+    let settings = {
+        let mut s = state.channel_settings.lock();
+        match device {
+            Some(name) => {
+                s.devices.insert(name, policy);
+            }
+            None => s.default_policy = policy,
+        }
+        s.clone()
+    };
+
+    let mut config = load_config(&app);
+    config["channels"] = serde_json::json!(settings);
+    save_config(&app, &config)?;
+
+    Ok(())
+}
+
+/// Switching takes effect immediately: the stream supervisor picks the new
+/// selection up and rebuilds the stream (an `"audio_device"` event follows).
 #[tauri::command]
 pub fn set_audio_device(
     app: AppHandle,
     state: State<'_, AppState>,
-    name: String,
+    id: String,
 ) -> Result<(), String> {
-    *state.selected_device.lock() = Some(name.clone());
+    *state.selected_device.lock() = Some(id.clone());
 
-    // Persist to config.json
+    // Persist to config.json ("device" held a name before device ids)
     let mut config = load_config(&app);
-    config["device"] = serde_json::json!(name);
+    config["device_id"] = serde_json::json!(id);
+    if let Some(c) = config.as_object_mut() {
+        c.remove("device");
+    }
     save_config(&app, &config)?;
 
     Ok(())
@@ -29,12 +89,410 @@ pub fn get_audio_device(state: State<'_, AppState>) -> Option<String> {
 }
 
 #[tauri::command]
-pub fn save_hotkey(app: AppHandle, modifiers: Vec<String>, code: String) -> Result<(), String> {
-    use tauri_plugin_global_shortcut::{Code, Modifiers};
-    let state = app.state::<AppState>();
+pub fn get_active_audio_device(state: State<'_, AppState>) -> DeviceStatus {
+    state.active_device.lock().clone()
+}
+
+/// Back-pressure counters of the audio hand-off (dropped/late samples, backlog).
+#[tauri::command]
+pub fn get_audio_diagnostics(state: State<'_, AppState>) -> HandoffStats {
+    state
+        .tx_audio
+        .lock()
+        .as_ref()
+        .map(|tx| tx.stats())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_vad_settings(state: State<'_, AppState>) -> VadSettings {
+    state.vad_settings.lock().clone()
+}
+
+#[tauri::command]
+pub fn set_vad_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: VadSettings,
+) -> Result<(), String> {
+    if settings.aggressiveness > 3 {
+        return Err("VAD aggressiveness must be between 0 and 3".to_string());
+    }
+    if !(0.0..=1.0).contains(&settings.silero_threshold) {
+        return Err("Silero threshold must be between 0.0 and 1.0".to_string());
+    }
+    // Takes effect on the next recording (the detector re-reads settings on start).
+    // The Silero backend expects `silero_vad.onnx` in the app data dir.
+    *state.vad_settings.lock() = settings.clone();
+
+    let mut config = load_config(&app);
+    config["vad"] = serde_json::json!(settings);
+    save_config(&app, &config)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_denoise_settings(state: State<'_, AppState>) -> DenoiseSettings {
+    state.denoise_settings.lock().clone()
+}
+
+/// Toggle noise suppression for one device (id), or the default when `device` is None.
+/// The running stream picks the change up on its next callback.
+#[tauri::command]
+pub fn set_denoise_enabled(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    device: Option<String>,
+    enabled: bool,
+) -> Result<(), String> {
+    let settings = {
+        let mut s = state.denoise_settings.lock();
+        match device {
+            Some(name) => {
+                s.devices.insert(name, enabled);
+            }
+            None => s.default_enabled = enabled,
+        }
+        s.clone()
+    };
+
+    let mut config = load_config(&app);
+    config["denoise"] = serde_json::json!(settings);
+    save_config(&app, &config)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_gain_settings(state: State<'_, AppState>) -> GainSettings {
+    state.gain_settings.lock().clone()
+}
+
+#[tauri::command]
+pub fn set_gain_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: GainSettings,
+) -> Result<(), String> {
+    if !(0.0..=40.0).contains(&settings.max_gain_db) {
+        return Err("Maximum gain must be between 0 and 40 dB".to_string());
+    }
+    if !(-40.0..=-6.0).contains(&settings.target_dbfs) {
+        return Err("Target level must be between -40 and -6 dBFS".to_string());
+    }
+    if !(0.5..=1.0).contains(&settings.clip_threshold) {
+        return Err("Clip threshold must be between 0.5 and 1.0".to_string());
+    }
+    if !(-90.0..=0.0).contains(&settings.quiet_dbfs) {
+        return Err("Too-quiet level must be between -90 and 0 dBFS".to_string());
+    }
+    // The running stream reads these on every callback
+    *state.gain_settings.lock() = settings.clone();
+
+    let mut config = load_config(&app);
+    config["gain"] = serde_json::json!(settings);
+    save_config(&app, &config)?;
 
+    Ok(())
+}
+
+/// Keeps `"audio_meter"` coming while a visualizer is mounted (`active` = false on unmount).
+#[tauri::command]
+pub fn watch_audio_meter(state: State<'_, AppState>, active: bool) {
+    let mut watchers = state.meter_watchers.lock();
+    *watchers = if active {
+        *watchers + 1
+    } else {
+        watchers.saturating_sub(1)
+    };
+}
+
+#[tauri::command]
+pub fn get_mix_settings(state: State<'_, AppState>) -> MixSettings {
+    state.mix_settings.lock().clone()
+}
+
+/// Mic + system audio mix. Turning it on/off or changing the monitor rebuilds the
+/// stream; gains apply on the next callback.
+#[tauri::command]
+pub fn set_mix_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: MixSettings,
+) -> Result<(), String> {
+    if !(-40.0..=20.0).contains(&settings.mic_gain_db)
+        || !(-40.0..=20.0).contains(&settings.system_gain_db)
+    {
+        return Err("Source gains must be between -40 and +20 dB".to_string());
+    }
+    if let Some(monitor) = &settings.monitor {
+        if loopback::monitor_name(monitor).is_none() {
+            return Err(format!("\"{}\" is not a system audio device", monitor));
+        }
+    }
+    *state.mix_settings.lock() = settings.clone();
+
+    let mut config = load_config(&app);
+    config["loopback"] = serde_json::json!(settings);
+    save_config(&app, &config)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_wake_word_settings(state: State<'_, AppState>) -> WakeWordSettings {
+    state.wake_word.lock().clone()
+}
+
+/// Wake-word activation. Enabling needs the phrase's model
+/// (`wakeword_<phrase>.onnx`) in the app data dir; the stream loads it on its
+/// next callback.
+#[tauri::command]
+pub fn set_wake_word_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: WakeWordSettings,
+) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&settings.threshold) {
+        return Err("Wake-word threshold must be between 0.0 and 1.0".to_string());
+    }
+    if settings.enabled {
+        let model_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        let path = wakeword::model_path(&model_dir, &settings.phrase);
+        if !path.exists() {
+            return Err(format!(
+                "No wake-word model for \"{}\" (expected {})",
+                settings.phrase,
+                path.display()
+            ));
+        }
+    }
+    *state.wake_word.lock() = settings.clone();
+
+    let mut config = load_config(&app);
+    config["wake_word"] = serde_json::json!(settings);
+    save_config(&app, &config)?;
+
+    Ok(())
+}
+
+/// Calibration wizard, step 1 and 2: record `phase` (silence first, then speech)
+/// on the selected device for `seconds` (default 3 / 5) and return its levels.
+/// Taps the always-on stream rather than opening the device a second time.
+#[tauri::command]
+pub async fn run_calibration_step(
+    state: State<'_, AppState>,
+    phase: CalibrationPhase,
+    seconds: Option<u32>,
+) -> Result<PhaseMeasurement, String> {
+    if *state.is_recording.lock() {
+        return Err("Stop the recording before calibrating".to_string());
+    }
+    let seconds = seconds
+        .unwrap_or(phase.default_seconds())
+        .clamp(1, calibration::MAX_PHASE_SECONDS);
+    // Measured on the running stream, so it has to be on the selected device;
+    // a profile for a fallback is useless
+    let status = state.active_device.lock().clone();
+    let Some(active) = status.active.filter(|_| !status.fallback) else {
+        return Err(format!(
+            "Selected device \"{}\" is not available",
+            status.requested.as_deref().unwrap_or("default")
+        ));
+    };
+    let info = AudioEngine::list_devices()
+        .into_iter()
+        .find(|d| d.id == active)
+        .ok_or_else(|| format!("Device {} is gone", active))?;
+
+    let tap = state.calibration_tap.clone();
+    let measurement = tauri::async_runtime::spawn_blocking(move || {
+        calibration::record_phase(phase, seconds, &tap)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let mut run = state.calibration_run.lock();
+    // Measurements from different devices don't make one profile
+    if run.device.as_ref().map(|d| &d.id) != Some(&info.id) {
+        run.silence = None;
+        run.speech = None;
+    }
+    match phase {
+        CalibrationPhase::Silence => run.silence = Some(measurement.clone()),
+        CalibrationPhase::Speech => run.speech = Some(measurement.clone()),
+    }
+    run.device = Some(info);
+    Ok(measurement)
+}
+
+/// Calibration wizard, last step: suggested VAD and gain settings from the two
+/// measurements. With `save`, they become the device's profile (`"calibration"`
+/// in config.json), which overrides the global settings on that device.
+#[tauri::command]
+pub fn finish_calibration(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    save: bool,
+) -> Result<CalibrationResult, String> {
+    let result = {
+        let run = state.calibration_run.lock();
+        let (Some(device), Some(silence), Some(speech)) = (&run.device, &run.silence, &run.speech)
+        else {
+            return Err("Record the silence and the speech step first".to_string());
+        };
+        calibration::suggest(&device.id, silence, speech, &state.gain_settings.lock())
+    };
+    println!("[DEBUG] Calibration result: {:?}", result);
+
+    if save {
+        if result.issues.contains(&calibration::CalibrationIssue::NoSpeech) {
+            return Err("No speech was picked up; check the microphone and try again".to_string());
+        }
+        let profiles = {
+            let mut profiles = state.calibration.lock();
+            profiles
+                .devices
+                .insert(result.device.clone(), result.profile.clone());
+            profiles.clone()
+        };
+        let mut config = load_config(&app);
+        config["calibration"] = serde_json::json!(profiles);
+        save_config(&app, &config)?;
+        *state.calibration_run.lock() = Default::default();
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn get_calibration_profiles(state: State<'_, AppState>) -> CalibrationProfiles {
+    state.calibration.lock().clone()
+}
+
+/// Drops a device's profile (id); the global gain and VAD settings apply again.
+#[tauri::command]
+pub fn delete_calibration_profile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    device: String,
+) -> Result<(), String> {
+    let profiles = {
+        let mut profiles = state.calibration.lock();
+        profiles.devices.remove(&device);
+        profiles.clone()
+    };
+    let mut config = load_config(&app);
+    config["calibration"] = serde_json::json!(profiles);
+    save_config(&app, &config)
+}
+
+/// Input levels of the most recent recording (also emitted as `"level_stats"`).
+#[tauri::command]
+pub fn get_last_level_stats(state: State<'_, AppState>) -> Option<LevelStats> {
+    state.level_report.lock().last_session.clone()
+}
+
+#[tauri::command]
+pub fn get_archive_settings(state: State<'_, AppState>) -> ArchiveSettings {
+    state.archive.settings()
+}
+
+/// Enabling/limiting the archive applies immediately; disabling it keeps existing
+/// sessions until they expire or are cleared.
+#[tauri::command]
+pub fn set_archive_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: ArchiveSettings,
+) -> Result<(), String> {
+    state.archive.set_settings(settings.clone());
+    state.archive.enforce_retention().map_err(|e| e.to_string())?;
+
+    let mut config = load_config(&app);
+    config["archive"] = serde_json::json!(settings);
+    save_config(&app, &config)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_archived_sessions(state: State<'_, AppState>) -> Result<Vec<SessionMeta>, String> {
+    let archive = state.archive.clone();
+    tauri::async_runtime::spawn_blocking(move || archive.list())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_archived_session(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    state.archive.delete(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_archive(state: State<'_, AppState>) -> Result<(), String> {
+    state.archive.clear().map_err(|e| e.to_string())
+}
+
+/// Decrypted session audio as a base64 WAV (16 kHz mono) for playback in the UI.
+#[tauri::command]
+pub async fn get_archived_session_audio(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<String, String> {
+    use base64::Engine;
+
+    let archive = state.archive.clone();
+    let audio = tauri::async_runtime::spawn_blocking(move || archive.load_audio(&id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16000,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut wav = zeroize::Zeroizing::new(Vec::new());
+    {
+        let mut writer = hound::WavWriter::new(std::io::Cursor::new(&mut *wav), spec)
+            .map_err(|e| e.to_string())?;
+        for &s in audio.as_slice() {
+            writer.write_sample(s).map_err(|e| e.to_string())?;
+        }
+        writer.finalize().map_err(|e| e.to_string())?;
+    }
+    Ok(base64::engine::general_purpose::STANDARD.encode(&*wav))
+}
+
+/// Re-runs Whisper on an archived session with the currently selected model.
+/// Returns the raw transcript; nothing is pasted.
+#[tauri::command]
+pub async fn retranscribe_archived_session(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<String, String> {
+    let archive = state.archive.clone();
+    let engine = state.inference_engine.clone();
+    let model = state.selected_model.lock().clone();
+
+    let transcript = tauri::async_runtime::spawn_blocking(move || {
+        let audio = archive.load_audio(&id).map_err(|e| e.to_string())?;
+        engine.transcribe(&model, audio.as_slice())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    Ok(transcript.as_str().to_string())
+}
+
+/// Maps the UI's modifier names and key label to a global shortcut.
+pub fn parse_hotkey(modifiers: &[String], code: &str) -> (Modifiers, Code) {
     let mut m = Modifiers::empty();
-    for mod_str in modifiers.clone() {
+    for mod_str in modifiers {
         match mod_str.to_uppercase().as_str() {
             "CTRL" | "CONTROL" => m |= Modifiers::CONTROL,
             "SHIFT" => m |= Modifiers::SHIFT,
@@ -73,22 +531,80 @@ pub fn save_hotkey(app: AppHandle, modifiers: Vec<String>, code: String) -> Resu
         "T" => Code::KeyT,
         "U" => Code::KeyU,
         "V" => Code::KeyV,
-        "W" => Code::KeyX,
+        "W" => Code::KeyW,
         "X" => Code::KeyX,
         "Y" => Code::KeyY,
         "Z" => Code::KeyZ,
         _ => Code::F9,
     };
+    (m, c)
+}
+
+fn hotkey_label(mods: Modifiers, code: Code) -> String {
+    let mut label = String::new();
+    if mods.contains(Modifiers::CONTROL) {
+        label.push_str("Ctrl + ");
+    }
+    if mods.contains(Modifiers::SHIFT) {
+        label.push_str("Shift + ");
+    }
+    if mods.contains(Modifiers::ALT) {
+        label.push_str("Alt + ");
+    }
+    if mods.contains(Modifiers::SUPER) {
+        label.push_str("Super + ");
+    }
+
+    label.push_str(&format!("{:?}", code));
+    label
+}
+
+// The global hotkeys; a chord can only trigger one of them
+#[derive(Clone, Copy, PartialEq)]
+enum HotkeySlot {
+    Main,
+    Capture,
+    Translate,
+}
+
+// Fails when `chord` is already taken by a hotkey other than `slot`
+fn ensure_hotkey_free(
+    state: &AppState,
+    slot: HotkeySlot,
+    chord: (Modifiers, Code),
+) -> Result<(), String> {
+    let main = (*state.hotkey_modifiers.lock(), *state.hotkey_code.lock());
+    let capture = *state.capture_hotkey.lock();
+    let translate = *state.translate_hotkey.lock();
+    let assigned = [
+        (HotkeySlot::Main, Some(main), "recording hotkey"),
+        (HotkeySlot::Capture, capture, "capture hotkey"),
+        (HotkeySlot::Translate, translate, "translate hotkey"),
+    ];
+    for (other, taken, name) in assigned {
+        if other != slot && taken == Some(chord) {
+            return Err(format!(
+                "{} is already the {}",
+                hotkey_label(chord.0, chord.1),
+                name
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn save_hotkey(app: AppHandle, modifiers: Vec<String>, code: String) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let (m, c) = parse_hotkey(&modifiers, &code);
+    ensure_hotkey_free(&state, HotkeySlot::Main, (m, c))?;
 
     *state.hotkey_modifiers.lock() = m;
     *state.hotkey_code.lock() = c;
 
-    // Persist to config.json
+    // Persist to config.json, keeping the hotkey's mode
     let mut config = load_config(&app);
-    config["hotkey"] = serde_json::json!({
-        "modifiers": modifiers,
-        "code": code
-    });
+    set_hotkey_chord(&mut config, &modifiers, &code);
     save_config(&app, &config)?;
 
     crate::re_register_shortcut(&app).map_err(|e| e.to_string())?;
@@ -97,25 +613,146 @@ pub fn save_hotkey(app: AppHandle, modifiers: Vec<String>, code: String) -> Resu
 
 #[tauri::command]
 pub fn get_hotkey(state: State<'_, AppState>) -> String {
-    let mods = state.hotkey_modifiers.lock();
-    let code = state.hotkey_code.lock();
+    let mods = *state.hotkey_modifiers.lock();
+    let code = *state.hotkey_code.lock();
+    hotkey_label(mods, code)
+}
 
-    let mut label = String::new();
-    if mods.contains(tauri_plugin_global_shortcut::Modifiers::CONTROL) {
-        label.push_str("Ctrl + ");
+#[tauri::command]
+pub fn get_hotkey_behavior(state: State<'_, AppState>) -> HotkeyBehavior {
+    *state.hotkey_behavior.lock()
+}
+
+/// Switches the main hotkey between toggle and push-to-talk. Stored in the
+/// `"hotkey"` entry of config.json next to the chord.
+#[tauri::command]
+pub fn set_hotkey_behavior(app: AppHandle, behavior: HotkeyBehavior) -> Result<(), String> {
+    if behavior.min_hold_ms > 2000 {
+        return Err("min_hold_ms must be at most 2000".to_string());
     }
-    if mods.contains(tauri_plugin_global_shortcut::Modifiers::SHIFT) {
-        label.push_str("Shift + ");
+    let state = app.state::<AppState>();
+    *state.hotkey_behavior.lock() = behavior;
+
+    let mut config = load_config(&app);
+    if !config["hotkey"].is_object() {
+        config["hotkey"] = serde_json::json!({});
     }
-    if mods.contains(tauri_plugin_global_shortcut::Modifiers::ALT) {
-        label.push_str("Alt + ");
+    config["hotkey"]["mode"] = serde_json::json!(behavior.mode);
+    config["hotkey"]["min_hold_ms"] = serde_json::json!(behavior.min_hold_ms);
+    save_config(&app, &config)
+}
+
+// Writes the chord into `config["hotkey"]`, leaving "mode"/"min_hold_ms" alone
+fn set_hotkey_chord(config: &mut serde_json::Value, modifiers: &[String], code: &str) {
+    if !config["hotkey"].is_object() {
+        config["hotkey"] = serde_json::json!({});
     }
-    if mods.contains(tauri_plugin_global_shortcut::Modifiers::SUPER) {
-        label.push_str("Super + ");
+    config["hotkey"]["modifiers"] = serde_json::json!(modifiers);
+    config["hotkey"]["code"] = serde_json::json!(code);
+}
+
+/// Sets (or with `code: None` removes) the retroactive-capture hotkey.
+#[tauri::command]
+pub fn save_capture_hotkey(
+    app: AppHandle,
+    modifiers: Vec<String>,
+    code: Option<String>,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let chord = code.as_deref().map(|c| parse_hotkey(&modifiers, c));
+    if let Some(chord) = chord {
+        ensure_hotkey_free(&state, HotkeySlot::Capture, chord)?;
     }
+    *state.capture_hotkey.lock() = chord;
 
-    label.push_str(&format!("{:?}", *code));
-    label
+    let mut config = load_config(&app);
+    config["capture_hotkey"] = match &code {
+        Some(c) => serde_json::json!({ "modifiers": modifiers, "code": c }),
+        None => serde_json::Value::Null,
+    };
+    save_config(&app, &config)?;
+
+    crate::re_register_shortcut(&app).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_capture_hotkey(state: State<'_, AppState>) -> Option<String> {
+    state
+        .capture_hotkey
+        .lock()
+        .map(|(mods, code)| hotkey_label(mods, code))
+}
+
+/// Sets (or with `code: None` removes) the hotkey that flips the translate toggle.
+#[tauri::command]
+pub fn save_translate_hotkey(
+    app: AppHandle,
+    modifiers: Vec<String>,
+    code: Option<String>,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let chord = code.as_deref().map(|c| parse_hotkey(&modifiers, c));
+    if let Some(chord) = chord {
+        ensure_hotkey_free(&state, HotkeySlot::Translate, chord)?;
+    }
+    *state.translate_hotkey.lock() = chord;
+
+    let mut config = load_config(&app);
+    config["translate_hotkey"] = match &code {
+        Some(c) => serde_json::json!({ "modifiers": modifiers, "code": c }),
+        None => serde_json::Value::Null,
+    };
+    save_config(&app, &config)?;
+
+    crate::re_register_shortcut(&app).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_translate_hotkey(state: State<'_, AppState>) -> Option<String> {
+    state
+        .translate_hotkey
+        .lock()
+        .map(|(mods, code)| hotkey_label(mods, code))
+}
+
+#[tauri::command]
+pub fn get_capture_seconds(state: State<'_, AppState>) -> u32 {
+    *state.capture_secs.lock()
+}
+
+/// How far back retroactive capture reaches. Resizes the always-on ring buffer,
+/// so longer keeps more audio in memory (16 kHz f32 = 64 KB per second); the
+/// pre-roll of a recording stays 3 s.
+#[tauri::command]
+pub fn set_capture_seconds(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    seconds: u32,
+) -> Result<(), String> {
+    if seconds > MAX_CAPTURE_SECONDS {
+        return Err(format!("Capture can be at most {} seconds", MAX_CAPTURE_SECONDS));
+    }
+    *state.capture_secs.lock() = seconds;
+
+    let mut config = load_config(&app);
+    config["capture_seconds"] = serde_json::json!(seconds);
+    save_config(&app, &config)?;
+
+    Ok(())
+}
+
+/// Transcribes and pastes the last `seconds` of the ring buffer (default: all of it)
+/// without a recording having been started.
+#[tauri::command]
+pub fn capture_recent_audio(state: State<'_, AppState>, seconds: Option<u32>) -> Result<(), String> {
+    if *state.is_recording.lock() {
+        return Err("Already recording".to_string());
+    }
+    let seconds = seconds.unwrap_or_else(|| *state.capture_secs.lock());
+    *state.capture_request.lock() = Some(seconds);
+    Ok(())
 }
 
 #[tauri::command]
@@ -136,6 +773,19 @@ pub async fn download_model(app: AppHandle, tier: String) -> Result<(), String>
             "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en.bin",
             "ggml-small.en.bin",
         ),
+        // Multilingual variants, needed for languages other than English and "auto"
+        "realfast_multi" => (
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.bin",
+            "ggml-tiny.bin",
+        ),
+        "fast_multi" => (
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.bin",
+            "ggml-base.bin",
+        ),
+        "standard_multi" => (
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.bin",
+            "ggml-small.bin",
+        ),
         "pro" => (
             "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3-turbo.bin",
             "ggml-large-v3-turbo.bin",
@@ -145,12 +795,9 @@ pub async fn download_model(app: AppHandle, tier: String) -> Result<(), String>
 
     let model_path = app.path().app_data_dir().unwrap().join(filename);
 
-    // Save preference to app config (simulated for now by updating state)
-    let state = app.state::<AppState>();
-    *state.selected_model.lock() = filename.to_string();
-
     if model_path.exists() {
         println!("[DEBUG] Model {} already exists", filename);
+        select_model(&app, filename)?;
         app.emit("download-progress", 100)
             .map_err(|e| e.to_string())?;
         return Ok(());
@@ -165,7 +812,9 @@ pub async fn download_model(app: AppHandle, tier: String) -> Result<(), String>
     let response = reqwest::get(model_url).await.map_err(|e| e.to_string())?;
     let total_size = response.content_length().unwrap_or(0);
 
-    let mut file = tokio::fs::File::create(&model_path)
+    // Download next to the model and rename when done, so a half-written file is never loaded
+    let partial_path = model_path.with_extension("bin.part");
+    let mut file = tokio::fs::File::create(&partial_path)
         .await
         .map_err(|e: std::io::Error| e.to_string())?;
     let mut downloaded: u64 = 0;
@@ -184,17 +833,247 @@ pub async fn download_model(app: AppHandle, tier: String) -> Result<(), String>
         }
     }
 
+    file.flush().await.map_err(|e: std::io::Error| e.to_string())?;
+    drop(file);
+    std::fs::rename(&partial_path, &model_path).map_err(|e| e.to_string())?;
+
     println!("[DEBUG] Model downloaded to {:?}", model_path);
+    select_model(&app, filename)?;
     app.emit("download-progress", 100)
         .map_err(|e| e.to_string())?;
     Ok(())
 }
 
+// Only a complete model file is selected: the model manager swaps to it right away
+fn select_model(app: &AppHandle, filename: &str) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    *state.selected_model.lock() = filename.to_string();
+
+    let mut config = load_config(app);
+    config["model"] = serde_json::json!(filename);
+    save_config(app, &config)
+}
+
 #[tauri::command]
 pub fn get_selected_model(state: tauri::State<AppState>) -> String {
     state.selected_model.lock().clone()
 }
 
+/// Which model is selected and which one is resident in memory.
+#[tauri::command]
+pub fn get_model_status(state: State<'_, AppState>) -> ModelStatus {
+    state.inference_engine.models().status()
+}
+
+/// Idle unload and preload of the Whisper model. `idle_unload_secs: 0` keeps it loaded.
+#[tauri::command]
+pub fn set_model_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: ModelSettings,
+) -> Result<(), String> {
+    *state.model_settings.lock() = settings.clone();
+
+    let mut config = load_config(&app);
+    config["models"] = serde_json::json!(settings);
+    save_config(&app, &config)
+}
+
+#[tauri::command]
+pub fn get_language_settings(state: State<'_, AppState>) -> LanguageSettings {
+    state.language_settings.lock().clone()
+}
+
+/// Default transcription language (`"auto"` to detect it) and per-app rules.
+/// Applies from the next dictation.
+#[tauri::command]
+pub fn set_language_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    mut settings: LanguageSettings,
+) -> Result<(), String> {
+    settings.language = normalize_language(&settings.language)?;
+    for rule in settings.rules.iter_mut() {
+        if rule.app.trim().is_empty() {
+            return Err("Language rules need an app name".to_string());
+        }
+        rule.language = normalize_language(&rule.language)?;
+    }
+    let model = state.selected_model.lock().clone();
+    if settings.translate && language::is_english_only(&model) {
+        return Err(format!(
+            "{} is English-only and can't translate. Download a multilingual model first.",
+            model
+        ));
+    }
+    if language::is_english_only(&model) && settings.language != "en" {
+        println!(
+            "[WARNING] {} is English-only; \"{}\" needs a multilingual model.",
+            model, settings.language
+        );
+    }
+    *state.language_settings.lock() = settings.clone();
+    crate::sync_translate_tray(&app, settings.translate);
+    let _ = app.emit("translate_mode", settings.translate);
+
+    let mut config = load_config(&app);
+    config["language"] = serde_json::json!(settings);
+    save_config(&app, &config)
+}
+
+/// Translate toggle: the final pass of each dictation comes out in English.
+#[tauri::command]
+pub fn set_translate_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    apply_translate(&app, enabled)
+}
+
+/// Sets the translate toggle from the UI, tray or translate hotkey, persists it
+/// and reports it as `translate_mode`.
+pub fn apply_translate(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let model = state.selected_model.lock().clone();
+    if enabled && language::is_english_only(&model) {
+        return Err(format!(
+            "{} is English-only and can't translate. Download a multilingual model first.",
+            model
+        ));
+    }
+    let settings = {
+        let mut settings = state.language_settings.lock();
+        settings.translate = enabled;
+        settings.clone()
+    };
+    crate::sync_translate_tray(app, enabled);
+    let _ = app.emit("translate_mode", enabled);
+
+    let mut config = load_config(app);
+    config["language"] = serde_json::json!(settings);
+    save_config(app, &config)
+}
+
+#[tauri::command]
+pub fn get_decoding_settings(state: State<'_, AppState>) -> DecodingSettings {
+    state.decoding_settings.lock().clone()
+}
+
+/// Decoding profiles for the partial (ghost text) and final passes. Used from
+/// the next Whisper pass on, even mid-dictation.
+#[tauri::command]
+pub fn set_decoding_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: DecodingSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    *state.decoding_settings.lock() = settings.clone();
+
+    let mut config = load_config(&app);
+    config["decoding"] = serde_json::json!(settings);
+    save_config(&app, &config)
+}
+
+#[tauri::command]
+pub fn get_transcript_settings(state: State<'_, AppState>) -> TranscriptSettings {
+    state.transcript_settings.lock().clone()
+}
+
+/// `reject_garbage`: drop transcripts that look like hallucinations or noise
+/// (reported as `transcript_rejected` with the text) instead of pasting them.
+#[tauri::command]
+pub fn set_transcript_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: TranscriptSettings,
+) -> Result<(), String> {
+    *state.transcript_settings.lock() = settings.clone();
+
+    let mut config = load_config(&app);
+    config["transcript"] = serde_json::json!(settings);
+    save_config(&app, &config)
+}
+
+#[tauri::command]
+pub fn get_dictionary(state: State<'_, AppState>) -> Dictionary {
+    state.dictionary.lock().clone()
+}
+
+/// Replaces the user dictionary; used from the next dictation on.
+#[tauri::command]
+pub fn set_dictionary(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    dictionary: Dictionary,
+) -> Result<Dictionary, String> {
+    let dictionary = dictionary.normalized();
+    if dictionary
+        .scopes
+        .iter()
+        .any(|s| s.app.is_none() && s.mode.is_none())
+    {
+        return Err("Each dictionary scope needs an app or a mode".to_string());
+    }
+    store_dictionary(&app, &state, dictionary)
+}
+
+/// Adds a word for every app, or only for `app_name` and/or `mode`
+/// (e.g. `"Coding"` for code identifiers).
+#[tauri::command]
+pub fn add_dictionary_word(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    word: String,
+    app_name: Option<String>,
+    mode: Option<AppMode>,
+) -> Result<Dictionary, String> {
+    if word.trim().is_empty() {
+        return Err("Word must not be empty".to_string());
+    }
+    let mut dictionary = state.dictionary.lock().clone();
+    dictionary.add(&word, app_name.as_deref(), mode);
+    store_dictionary(&app, &state, dictionary)
+}
+
+/// Removes a word from the list `add_dictionary_word` would put it in.
+#[tauri::command]
+pub fn remove_dictionary_word(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    word: String,
+    app_name: Option<String>,
+    mode: Option<AppMode>,
+) -> Result<Dictionary, String> {
+    let mut dictionary = state.dictionary.lock().clone();
+    if !dictionary.remove(&word, app_name.as_deref(), mode) {
+        return Err(format!("\"{}\" is not in the dictionary", word.trim()));
+    }
+    store_dictionary(&app, &state, dictionary)
+}
+
+// Saves to dictionary.json in the app data dir, then swaps it in
+fn store_dictionary(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    dictionary: Dictionary,
+) -> Result<Dictionary, String> {
+    let path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(DICTIONARY_FILE);
+    dictionary.save(&path).map_err(|e| e.to_string())?;
+    *state.dictionary.lock() = dictionary.clone();
+    Ok(dictionary)
+}
+
+fn normalize_language(code: &str) -> Result<String, String> {
+    let code = code.trim().to_lowercase();
+    if code == language::AUTO || whisper_rs::get_lang_id(&code).is_some() {
+        Ok(code)
+    } else {
+        Err(format!("Unknown language \"{}\"", code))
+    }
+}
+
 /// Load config from JSON file
 fn load_config(app: &AppHandle) -> serde_json::Value {
     let config_path = app.path().app_data_dir().unwrap().join("config.json");
@@ -246,17 +1125,17 @@ pub fn complete_onboarding(app: AppHandle) -> Result<(), String> {
         vec![]
     };
 
-    // Build config object with all settings
-    let config = serde_json::json!({
-        "onboarded": true,
-        "model": state.selected_model.lock().clone(),
-        "device": state.selected_device.lock().clone(),
-        "hotkey": {
-            "modifiers": modifiers,
-            "code": code
-        },
-        "version": "0.3.0"
-    });
+    // Merge onboarding results into the existing config so settings saved
+    // earlier (e.g. "vad") are not wiped
+    let mut config = load_config(&app);
+    config["onboarded"] = serde_json::json!(true);
+    config["model"] = serde_json::json!(state.selected_model.lock().clone());
+    config["device_id"] = serde_json::json!(state.selected_device.lock().clone());
+    if let Some(c) = config.as_object_mut() {
+        c.remove("device");
+    }
+    set_hotkey_chord(&mut config, &modifiers, &code);
+    config["version"] = serde_json::json!("0.3.0");
 
     save_config(&app, &config)?;
     println!("[DEBUG] Onboarding completed and saved to config.json");
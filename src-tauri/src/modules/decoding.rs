@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+// Whisper decoding is a trade between latency and accuracy that depends on the
+// machine. Ghost text needs fast passes; the text that gets pasted can afford
+// beam search and retries, so each has its own profile.
+
+// whisper.cpp runs at most this many decoders (best-of candidates or beams)
+const MAX_DECODERS: u32 = 8;
+// Far past where more threads stop helping; also keeps the count a valid i32
+const MAX_THREADS: u32 = 64;
+
+/// Decoding profiles, persisted as `"decoding"` in config.json.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodingSettings {
+    /// Streaming passes for the ghost text.
+    pub partial: DecodingProfile,
+    /// Passes whose text is kept: segment ends, the last window, translation
+    /// and one-shot transcription.
+    #[serde(rename = "final")]
+    pub final_pass: DecodingProfile,
+}
+
+/// Parameters for one kind of Whisper pass. Defaults are whisper.cpp's, with
+/// greedy decoding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodingProfile {
+    pub strategy: Strategy,
+    /// 0 is deterministic.
+    pub temperature: f32,
+    /// Temperature fallback: a pass that fails the thresholds below is retried
+    /// this much hotter, up to 1.0. 0 disables retries.
+    pub temperature_increment: f32,
+    /// Retry when the text's compression entropy is above this (repetitive).
+    pub entropy_threshold: f32,
+    /// Retry when the average token log probability is below this.
+    pub logprob_threshold: f32,
+    /// A segment this likely to be silence, with low log probability, is dropped.
+    pub no_speech_threshold: f32,
+    /// Whisper threads, at most 64; 0 picks up to 8 from the CPU count.
+    pub threads: u32,
+}
+
+impl Default for DecodingProfile {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::default(),
+            temperature: 0.0,
+            temperature_increment: 0.2,
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+            no_speech_threshold: 0.6,
+            threads: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Strategy {
+    /// Picks the likeliest token; `best_of` candidates when sampling hotter.
+    Greedy { best_of: u32 },
+    /// Keeps `beam_size` hypotheses: slower, usually more accurate.
+    BeamSearch { beam_size: u32 },
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::Greedy { best_of: 1 }
+    }
+}
+
+impl DecodingSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.partial
+            .validate()
+            .map_err(|e| format!("Partial pass: {}", e))?;
+        self.final_pass
+            .validate()
+            .map_err(|e| format!("Final pass: {}", e))
+    }
+}
+
+impl DecodingProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        let decoders = match self.strategy {
+            Strategy::Greedy { best_of } => best_of,
+            Strategy::BeamSearch { beam_size } => beam_size,
+        };
+        if !(1..=MAX_DECODERS).contains(&decoders) {
+            return Err(format!(
+                "best_of and beam_size must be between 1 and {}",
+                MAX_DECODERS
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.temperature) {
+            return Err("Temperature must be between 0.0 and 1.0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.temperature_increment) {
+            return Err("Temperature increment must be between 0.0 and 1.0".to_string());
+        }
+        if self.entropy_threshold.is_nan() || self.entropy_threshold <= 0.0 {
+            return Err("Entropy threshold must be positive".to_string());
+        }
+        if self.logprob_threshold.is_nan() || self.logprob_threshold > 0.0 {
+            return Err("Log probability threshold must not be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.no_speech_threshold) {
+            return Err("No-speech threshold must be between 0.0 and 1.0".to_string());
+        }
+        if self.threads > MAX_THREADS {
+            return Err(format!("Threads must be at most {}", MAX_THREADS));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use nnnoiseless::DenoiseState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// RNNoise works on 10 ms frames of 48 kHz mono audio.
+pub const DENOISE_SAMPLE_RATE: u32 = 48000;
+const FRAME_SIZE: usize = DenoiseState::FRAME_SIZE;
+
+/// Per-device noise suppression switch, persisted as `"denoise"` in config.json.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DenoiseSettings {
+    /// Used for devices that have no explicit entry.
+    pub default_enabled: bool,
+    /// Device id -> on/off.
+    pub devices: HashMap<String, bool>,
+}
+
+impl DenoiseSettings {
+    pub fn enabled_for(&self, device_id: &str) -> bool {
+        self.devices
+            .get(device_id)
+            .copied()
+            .unwrap_or(self.default_enabled)
+    }
+}
+
+/// Streaming RNNoise wrapper. Accepts arbitrary-length 48 kHz chunks in
+/// [-1.0, 1.0] and returns denoised audio with one frame (10 ms) of latency.
+pub struct Denoiser {
+    state: Box<DenoiseState<'static>>,
+    pending: Vec<f32>,
+    frame_out: Vec<f32>,
+    first_frame: bool,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Self {
+            state: DenoiseState::new(),
+            pending: Vec::with_capacity(FRAME_SIZE * 2),
+            frame_out: vec![0.0; FRAME_SIZE],
+            first_frame: true,
+        }
+    }
+
+    /// Starts over after a break in the input, without reallocating RNNoise's state.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.first_frame = true;
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        // RNNoise expects i16-range floats, not [-1.0, 1.0]
+        self.pending
+            .extend(samples.iter().map(|&s| s * i16::MAX as f32));
+
+        let mut output = Vec::with_capacity(self.pending.len());
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= FRAME_SIZE {
+            let frame = &self.pending[consumed..consumed + FRAME_SIZE];
+            self.state.process_frame(&mut self.frame_out, frame);
+            consumed += FRAME_SIZE;
+
+            if self.first_frame {
+                // The first output frame contains RNNoise's fade-in artifacts.
+                // Emit silence instead so the sample count (and timing) stays intact.
+                self.first_frame = false;
+                output.resize(output.len() + FRAME_SIZE, 0.0);
+            } else {
+                output.extend(self.frame_out.iter().map(|&s| s / i16::MAX as f32));
+            }
+        }
+        self.pending.drain(..consumed);
+        output
+    }
+}
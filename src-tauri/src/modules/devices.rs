@@ -0,0 +1,169 @@
+use crate::modules::loopback::LOOPBACK_ID_PREFIX;
+use serde::Serialize;
+use serde_json::Value;
+
+// config.json sections whose `devices` map holds per-device settings
+const DEVICE_SETTINGS_SECTIONS: [&str; 3] = ["channels", "denoise", "calibration"];
+
+// Rates worth listing when a device reports a continuous range
+const STANDARD_RATES: [u32; 10] = [
+    8000, 11025, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 192000,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    Microphone,
+    /// System audio: a PulseAudio/PipeWire monitor of an output (see loopback.rs).
+    Loopback,
+}
+
+/// One input device as offered to the UI (`get_audio_devices`).
+/// Select by `id`; per-device settings (channels, denoise, calibration) key on it too.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceInfo {
+    /// Stable across restarts as long as the host names the device the same way.
+    pub id: String,
+    /// Name as reported by cpal, e.g. `sysdefault:CARD=Q9`.
+    pub raw_name: String,
+    /// Cleaned-up name for pickers.
+    pub display_name: String,
+    /// Audio host (ALSA, WASAPI, CoreAudio, ...).
+    pub host: String,
+    /// Supported channel counts, ascending.
+    pub channels: Vec<u16>,
+    /// Channel count the stream opens the device with.
+    pub stream_channels: u16,
+    /// Standard rates inside the supported ranges, plus the range limits.
+    pub sample_rates: Vec<u32>,
+    pub sample_formats: Vec<String>,
+    pub is_default: bool,
+    pub kind: DeviceKind,
+}
+
+impl DeviceInfo {
+    /// Whether `reference` (an id, or a raw/display name from older configs) means this device.
+    pub fn matches(&self, reference: &str) -> bool {
+        self.id == reference || self.raw_name == reference || self.display_name == reference
+    }
+}
+
+pub fn device_id(host: &str, raw_name: &str) -> String {
+    format!("{}:{}", host.to_lowercase(), raw_name)
+}
+
+pub fn display_name(raw_name: &str) -> String {
+    if raw_name.to_uppercase().contains("Q9") {
+        "Q9 Microphone 🎙️".to_string()
+    } else if raw_name.starts_with("sysdefault:CARD=") || raw_name.starts_with("default:CARD=") {
+        raw_name
+            .replace("sysdefault:CARD=", "")
+            .replace("default:CARD=", "")
+            .split(',')
+            .next()
+            .unwrap_or(raw_name)
+            .to_string()
+    } else {
+        raw_name.to_string()
+    }
+}
+
+/// Flattens supported `(min, max)` rate ranges into a sorted list.
+pub fn rates_in_ranges(ranges: &[(u32, u32)]) -> Vec<u32> {
+    let mut rates: Vec<u32> = ranges
+        .iter()
+        .flat_map(|&(min, max)| {
+            STANDARD_RATES
+                .iter()
+                .copied()
+                .filter(move |&r| r >= min && r <= max)
+                .chain([min, max])
+        })
+        .collect();
+    rates.sort_unstable();
+    rates.dedup();
+    rates
+}
+
+/// Finds the device a stored selection refers to. Ids win over names; raw names
+/// over display names (several raw names can share one display name).
+pub fn resolve<'a>(reference: &str, devices: &'a [DeviceInfo]) -> Option<&'a DeviceInfo> {
+    devices
+        .iter()
+        .find(|d| d.id == reference)
+        .or_else(|| devices.iter().find(|d| d.raw_name == reference))
+        .or_else(|| devices.iter().find(|d| d.display_name == reference))
+}
+
+/// Rewrites a pre-id config.json selection (`"device"`: a name from the old picker)
+/// to `"device_id"`. Returns whether the config changed. A device that isn't
+/// plugged in stays as it is; stream lookup still accepts names, and the next
+/// start with the device present migrates it.
+pub fn migrate_device_config(config: &mut Value, devices: &[DeviceInfo]) -> bool {
+    let legacy = match config.get("device") {
+        Some(Value::String(name)) => name.clone(),
+        Some(Value::Null) => {
+            // Old configs stored null for "system default"
+            config.as_object_mut().map(|c| c.remove("device"));
+            return true;
+        }
+        _ => return false,
+    };
+    let Some(info) = resolve(&legacy, devices) else {
+        return false;
+    };
+
+    println!(
+        "[DEBUG] Migrated input device \"{}\" to id \"{}\"",
+        legacy, info.id
+    );
+    config["device_id"] = serde_json::json!(info.id);
+    if let Some(c) = config.as_object_mut() {
+        c.remove("device");
+    }
+    true
+}
+
+/// The id a per-device settings key from before device ids stands for, or `None`
+/// when `key` already is one. Those keys were the name the stream was opened
+/// with: a raw cpal name of `host`, or a monitor source name for system audio.
+pub fn settings_key_id(key: &str, host: &str) -> Option<String> {
+    let host_prefix = format!("{}:", host.to_lowercase());
+    if key.starts_with(&host_prefix) || key.starts_with(LOOPBACK_ID_PREFIX) {
+        None
+    } else if key.ends_with(".monitor") {
+        Some(format!("{}{}", LOOPBACK_ID_PREFIX, key))
+    } else {
+        Some(device_id(host, key))
+    }
+}
+
+/// Re-keys the channel, denoise and calibration settings of a config from before
+/// device ids by id. Returns whether the config changed. Ids are derived from the
+/// names, so this needs no device to be plugged in and runs once. When both a
+/// name and its id have an entry, the id's wins.
+pub fn migrate_device_settings(config: &mut Value, host: &str) -> bool {
+    let mut changed = false;
+    for section in DEVICE_SETTINGS_SECTIONS {
+        let Some(Value::Object(devices)) =
+            config.get_mut(section).and_then(|s| s.get_mut("devices"))
+        else {
+            continue;
+        };
+        let legacy: Vec<(String, String)> = devices
+            .keys()
+            .filter_map(|k| settings_key_id(k, host).map(|id| (k.clone(), id)))
+            .collect();
+        for (name, id) in legacy {
+            if let Some(value) = devices.remove(&name) {
+                devices.entry(id.clone()).or_insert(value);
+            }
+            println!(
+                "[DEBUG] Migrated {} settings of \"{}\" to id \"{}\"",
+                section, name, id
+            );
+            changed = true;
+        }
+    }
+    changed
+}
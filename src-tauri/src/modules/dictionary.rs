@@ -0,0 +1,201 @@
+use crate::modules::llm::AppMode;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Names, acronyms and jargon Whisper would otherwise misspell. The words for
+// the app being dictated into are handed to Whisper as its initial prompt,
+// which biases decoding towards their spelling.
+
+/// Stored in the app data dir, next to config.json.
+pub const DICTIONARY_FILE: &str = "dictionary.json";
+// Whisper only looks at the last ~220 prompt tokens, which the committed text
+// also needs (see `streaming`); this leaves it room
+const MAX_VOCABULARY_CHARS: usize = 400;
+
+/// The user dictionary.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dictionary {
+    /// Used in every app.
+    pub words: Vec<String>,
+    /// Extra words for some apps only.
+    pub scopes: Vec<DictionaryScope>,
+}
+
+/// Words for dictations into an app whose name contains `app` (case-insensitive,
+/// like the refinement modes) and whose context is `mode`, e.g. code identifiers
+/// only in `Coding`. Either may be left out; a scope without both never applies.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DictionaryScope {
+    pub app: Option<String>,
+    pub mode: Option<AppMode>,
+    pub words: Vec<String>,
+}
+
+impl DictionaryScope {
+    fn applies(&self, app_name: &str, mode: &AppMode) -> bool {
+        let app = self.app.as_deref().map(str::trim).filter(|a| !a.is_empty());
+        let app_matches = match app {
+            Some(a) => app_name.to_lowercase().contains(&a.to_lowercase()),
+            None => self.mode.is_some(),
+        };
+        app_matches && (self.mode.is_none() || self.mode.as_ref() == Some(mode))
+    }
+}
+
+impl Dictionary {
+    /// Missing or unreadable files give an empty dictionary.
+    pub fn load(path: &Path) -> Self {
+        let Ok(data) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&data) {
+            Ok(dictionary) => dictionary,
+            Err(e) => {
+                println!("[WARNING] Ignoring {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Trims words and drops empty ones and duplicates (ignoring case) within
+    /// each list.
+    pub fn normalized(mut self) -> Self {
+        dedup(&mut self.words);
+        for scope in &mut self.scopes {
+            dedup(&mut scope.words);
+            scope.app = scope
+                .app
+                .take()
+                .map(|a| a.trim().to_string())
+                .filter(|a| !a.is_empty());
+        }
+        self
+    }
+
+    /// Adds `word` to the global list, or to the scope for `app` and/or `mode`
+    /// (created if there is none yet).
+    pub fn add(&mut self, word: &str, app: Option<&str>, mode: Option<AppMode>) {
+        if let Some(words) = self.list_mut(app, mode, true) {
+            words.push(word.to_string());
+            dedup(words);
+        }
+    }
+
+    /// Removes `word` (ignoring case) from the list `add` would put it in.
+    /// Returns whether it was there; a scope left without words is dropped.
+    pub fn remove(&mut self, word: &str, app: Option<&str>, mode: Option<AppMode>) -> bool {
+        let key = word.trim().to_lowercase();
+        let Some(words) = self.list_mut(app, mode, false) else {
+            return false;
+        };
+        let before = words.len();
+        words.retain(|w| w.to_lowercase() != key);
+        let removed = words.len() < before;
+        self.scopes.retain(|s| !s.words.is_empty());
+        removed
+    }
+
+    /// Words for a dictation into `app_name`: those of matching scopes first,
+    /// then the global ones.
+    pub fn words_for(&self, app_name: &str, mode: &AppMode) -> Vec<&str> {
+        let mut words: Vec<&str> = Vec::new();
+        let scoped = self
+            .scopes
+            .iter()
+            .filter(|s| s.applies(app_name, mode))
+            .flat_map(|s| s.words.iter());
+        for word in scoped.chain(self.words.iter()) {
+            let word = word.trim();
+            let key = word.to_lowercase();
+            if !word.is_empty() && !words.iter().any(|w| w.to_lowercase() == key) {
+                words.push(word);
+            }
+        }
+        words
+    }
+
+    /// Initial-prompt text listing the words for `app_name`, empty without any.
+    /// Words that don't fit are left out, the app-specific ones are kept first.
+    pub fn vocabulary(&self, app_name: &str, mode: &AppMode) -> String {
+        let mut vocabulary = String::new();
+        for word in self.words_for(app_name, mode) {
+            if vocabulary.len() + word.len() + 2 > MAX_VOCABULARY_CHARS {
+                break;
+            }
+            if !vocabulary.is_empty() {
+                vocabulary.push_str(", ");
+            }
+            vocabulary.push_str(word);
+        }
+        if !vocabulary.is_empty() {
+            vocabulary.push('.');
+        }
+        vocabulary
+    }
+
+    // The global list without `app` and `mode`, else the scope with exactly these
+    fn list_mut(
+        &mut self,
+        app: Option<&str>,
+        mode: Option<AppMode>,
+        create: bool,
+    ) -> Option<&mut Vec<String>> {
+        let app = app.map(str::trim).filter(|a| !a.is_empty());
+        if app.is_none() && mode.is_none() {
+            return Some(&mut self.words);
+        }
+        let key = app.map(str::to_lowercase);
+        let index = self
+            .scopes
+            .iter()
+            .position(|s| s.app.as_deref().map(str::to_lowercase) == key && s.mode == mode);
+        let index = match index {
+            Some(index) => index,
+            None if create => {
+                self.scopes.push(DictionaryScope {
+                    app: app.map(str::to_string),
+                    mode,
+                    words: Vec::new(),
+                });
+                self.scopes.len() - 1
+            }
+            None => return None,
+        };
+        Some(&mut self.scopes[index].words)
+    }
+}
+
+/// Whisper's initial prompt: the vocabulary, then the text spoken before the
+/// audio (the committed words while streaming), which it continues from.
+pub fn initial_prompt(vocabulary: &str, context: &str) -> String {
+    match (vocabulary.is_empty(), context.is_empty()) {
+        (true, _) => context.to_string(),
+        (false, true) => vocabulary.to_string(),
+        (false, false) => format!("{} {}", vocabulary, context),
+    }
+}
+
+fn dedup(words: &mut Vec<String>) {
+    let mut seen: Vec<String> = Vec::new();
+    words.retain_mut(|word| {
+        *word = word.trim().to_string();
+        let key = word.to_lowercase();
+        if word.is_empty() || seen.contains(&key) {
+            return false;
+        }
+        seen.push(key);
+        true
+    });
+}
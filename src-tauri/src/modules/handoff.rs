@@ -0,0 +1,178 @@
+use crate::modules::audio::SensitiveAudio;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+// Hand-off from the audio callback to the inference loop. The old 100-slot
+// channel dropped audio (via try_send) whenever a partial Whisper pass kept the
+// consumer busy for more than a second; this queue grows instead, up to a cap.
+
+// How far the consumer may fall behind before audio is dropped.
+// 16000 samples/sec * 600 sec = 9.6M samples (~38 MB)
+pub const DEFAULT_CAPACITY_SAMPLES: usize = 16000 * 600;
+// Audio that waited longer than this for the consumer counts as late
+const LATE_AFTER: Duration = Duration::from_secs(1);
+
+/// Back-pressure counters since startup, reported by `get_audio_diagnostics`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HandoffStats {
+    /// Samples taken by the consumer.
+    pub delivered_samples: u64,
+    /// Samples discarded because the backlog hit the cap.
+    pub dropped_samples: u64,
+    /// Delivered samples that sat in the queue longer than a second.
+    pub late_samples: u64,
+    /// Current backlog.
+    pub queued_samples: u64,
+    pub max_queued_samples: u64,
+    /// Longest time a chunk waited for the consumer.
+    pub max_delay_ms: u64,
+}
+
+struct Queue {
+    chunks: VecDeque<(Instant, SensitiveAudio)>,
+    queued: usize,
+    // Set while dropping, so the overflow is logged once per episode
+    overflowing: bool,
+    stats: HandoffStats,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    notify: Notify,
+    senders: AtomicUsize,
+    capacity: usize,
+}
+
+/// Producer side. `push` never blocks on the consumer, so it is safe to call
+/// from the realtime audio callback.
+pub struct AudioSender {
+    shared: Arc<Shared>,
+}
+
+/// Consumer side, used like a `tokio::sync::mpsc::Receiver`.
+pub struct AudioReceiver {
+    shared: Arc<Shared>,
+}
+
+/// Creates a queue that holds up to `capacity_samples` undelivered samples.
+pub fn audio_queue(capacity_samples: usize) -> (AudioSender, AudioReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue {
+            chunks: VecDeque::new(),
+            queued: 0,
+            overflowing: false,
+            stats: HandoffStats::default(),
+        }),
+        notify: Notify::new(),
+        senders: AtomicUsize::new(1),
+        capacity: capacity_samples,
+    });
+    (
+        AudioSender {
+            shared: shared.clone(),
+        },
+        AudioReceiver { shared },
+    )
+}
+
+impl AudioSender {
+    /// Queues a chunk. Returns false if it was dropped because the backlog is full.
+    pub fn push(&self, audio: SensitiveAudio) -> bool {
+        let len = audio.as_slice().len();
+        if len == 0 {
+            return true;
+        }
+        {
+            let mut queue = self.shared.queue.lock();
+            if queue.queued + len > self.shared.capacity {
+                queue.stats.dropped_samples += len as u64;
+                if !queue.overflowing {
+                    queue.overflowing = true;
+                    println!(
+                        "[WARNING] Audio backlog full ({} samples), dropping audio until the consumer catches up.",
+                        queue.queued
+                    );
+                }
+                return false;
+            }
+            queue.overflowing = false;
+            queue.queued += len;
+            queue.chunks.push_back((Instant::now(), audio));
+            queue.stats.max_queued_samples =
+                queue.stats.max_queued_samples.max(queue.queued as u64);
+        }
+        self.shared.notify.notify_one();
+        true
+    }
+
+    pub fn stats(&self) -> HandoffStats {
+        stats(&self.shared)
+    }
+}
+
+impl Clone for AudioSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for AudioSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Wake the receiver so it can see the queue is closed
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+impl AudioReceiver {
+    /// Waits for the next chunk. Returns `None` once every sender is gone and
+    /// the queue is drained. Cancel-safe: nothing is lost if the future is dropped.
+    pub async fn recv(&mut self) -> Option<SensitiveAudio> {
+        loop {
+            if let Some(audio) = self.try_recv() {
+                return Some(audio);
+            }
+            if self.shared.senders.load(Ordering::SeqCst) == 0 {
+                return self.try_recv();
+            }
+            // notify_one stores a permit, so a push between the check and here still wakes us
+            self.shared.notify.notified().await;
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<SensitiveAudio> {
+        let mut queue = self.shared.queue.lock();
+        let (queued_at, audio) = queue.chunks.pop_front()?;
+        let len = audio.as_slice().len();
+        let delay = queued_at.elapsed();
+
+        queue.queued -= len;
+        queue.stats.delivered_samples += len as u64;
+        if delay > LATE_AFTER {
+            queue.stats.late_samples += len as u64;
+        }
+        queue.stats.max_delay_ms = queue.stats.max_delay_ms.max(delay.as_millis() as u64);
+        Some(audio)
+    }
+
+    pub fn stats(&self) -> HandoffStats {
+        stats(&self.shared)
+    }
+}
+
+fn stats(shared: &Shared) -> HandoffStats {
+    let queue = shared.queue.lock();
+    HandoffStats {
+        queued_samples: queue.queued as u64,
+        ..queue.stats.clone()
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyMode {
+    /// Press to start, press again to stop.
+    #[default]
+    Toggle,
+    /// Record while the chord is held.
+    PushToTalk,
+}
+
+/// How a recording hotkey behaves. Stored next to `modifiers`/`code` in the
+/// hotkey's config.json entry, e.g. `"hotkey": {..., "mode": "push_to_talk"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotkeyBehavior {
+    pub mode: HotkeyMode,
+    /// Push-to-talk: releases sooner than this are accidental taps and the
+    /// recording is thrown away instead of transcribed.
+    pub min_hold_ms: u64,
+}
+
+impl Default for HotkeyBehavior {
+    fn default() -> Self {
+        Self {
+            mode: HotkeyMode::Toggle,
+            min_hold_ms: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Start,
+    Stop,
+    /// Stop and drop the recording without transcribing it.
+    Discard,
+    Ignore,
+}
+
+/// Turns press/release events of one recording hotkey into actions.
+#[derive(Debug, Default)]
+pub struct HotkeyController {
+    // Push-to-talk: when the chord went down, if it started the recording
+    held_since: Option<Instant>,
+}
+
+impl HotkeyController {
+    pub fn handle(
+        &mut self,
+        behavior: HotkeyBehavior,
+        pressed: bool,
+        recording: bool,
+        now: Instant,
+    ) -> HotkeyAction {
+        match behavior.mode {
+            HotkeyMode::Toggle => {
+                self.held_since = None;
+                match (pressed, recording) {
+                    (true, true) => HotkeyAction::Stop,
+                    (true, false) => HotkeyAction::Start,
+                    (false, _) => HotkeyAction::Ignore,
+                }
+            }
+            HotkeyMode::PushToTalk if pressed => {
+                if recording {
+                    // Key repeat while held
+                    if self.held_since.is_some() {
+                        return HotkeyAction::Ignore;
+                    }
+                    // A recording started some other way (e.g. the tray): the chord ends it
+                    return HotkeyAction::Stop;
+                }
+                // Also recovers from a release that never arrived
+                self.held_since = Some(now);
+                HotkeyAction::Start
+            }
+            HotkeyMode::PushToTalk => {
+                let Some(since) = self.held_since.take() else {
+                    return HotkeyAction::Ignore;
+                };
+                if !recording {
+                    // Already ended (e.g. VAD auto-stop) while the chord was held
+                    HotkeyAction::Ignore
+                } else if now.duration_since(since) < Duration::from_millis(behavior.min_hold_ms) {
+                    HotkeyAction::Discard
+                } else {
+                    HotkeyAction::Stop
+                }
+            }
+        }
+    }
+}
+
+/// Numbers recording sessions, so the transcription of a discarded one (a
+/// push-to-talk tap) can be told apart from the next real dictation.
+#[derive(Debug, Default)]
+pub struct RecordingSessions {
+    current: u64,
+    discarded: Option<u64>,
+}
+
+impl RecordingSessions {
+    /// A recording started; returns its number.
+    pub fn start(&mut self) -> u64 {
+        self.current += 1;
+        self.current
+    }
+
+    /// The latest recording.
+    pub fn current(&self) -> u64 {
+        self.current
+    }
+
+    /// Drops the latest recording; later ones are unaffected.
+    pub fn discard_current(&mut self) {
+        self.discarded = Some(self.current);
+    }
+
+    pub fn is_discarded(&self, session: u64) -> bool {
+        self.discarded == Some(session)
+    }
+}
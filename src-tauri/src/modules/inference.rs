@@ -1,66 +1,165 @@
 use crate::modules::audio::SensitiveAudio;
+use crate::modules::decoding::{DecodingProfile, DecodingSettings, Strategy};
+use crate::modules::dictionary::{self, Dictionary};
+use crate::modules::handoff::AudioReceiver;
+use crate::modules::hotkeys::RecordingSessions;
+use crate::modules::language::{self, LanguageSettings};
+use crate::modules::llm::ContextEngine;
+use crate::modules::longform::{self, PauseTracker};
+use crate::modules::model_manager::ModelManager;
+use crate::modules::os_integration::OSIntegration;
+use crate::modules::streaming::{LocalAgreement, PartialTranscript, Word, WordBuilder};
+use crate::modules::transcript::{self, SensitiveTranscript};
+use crate::modules::vad::{VadSettings, WebRtcVad};
+use parking_lot::Mutex;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
-use tokio::sync::mpsc;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperState};
 
-// Security: Protected Transcript
-#[derive(Zeroize, ZeroizeOnDrop, Debug)]
-pub struct SensitiveTranscript(String);
+// Session audio kept for the archive (10 minutes); longer sessions are transcribed
+// in full but archived truncated
+const MAX_ARCHIVED_SAMPLES: usize = 16000 * 60 * 10;
+// "auto": keep the language detected once this much audio was heard
+const PIN_LANGUAGE_SAMPLES: usize = 16000 * 2;
 
-impl SensitiveTranscript {
-    pub fn new(s: String) -> Self {
-        Self(s)
-    }
-    pub fn as_str(&self) -> &str {
-        &self.0
-    }
+// A Whisper state and the model it runs, whose vocabulary turns the state's
+// token ids back into text
+struct Decoder {
+    ctx: Arc<WhisperContext>,
+    state: WhisperState,
+}
+
+// What a Whisper pass is for, which picks its decoding profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pass {
+    // Ghost text
+    Partial,
+    // Text that is kept
+    Final,
+    // Kept text, translated to English
+    Translate,
 }
 
 pub struct InferenceEngine {
-    base_path: std::path::PathBuf,
+    models: ModelManager,
+    language: Arc<Mutex<LanguageSettings>>,
+    // Words to bias decoding towards
+    dictionary: Arc<Mutex<Dictionary>>,
+    // Read at every pass, so changes apply mid-session
+    decoding: Arc<Mutex<DecodingSettings>>,
+    // Pauses split long sessions into segments
+    vad: Arc<Mutex<VadSettings>>,
 }
 
 impl InferenceEngine {
-    pub fn new(app_data_dir: std::path::PathBuf) -> Self {
+    pub fn new(
+        models: ModelManager,
+        language: Arc<Mutex<LanguageSettings>>,
+        dictionary: Arc<Mutex<Dictionary>>,
+        decoding: Arc<Mutex<DecodingSettings>>,
+        vad: Arc<Mutex<VadSettings>>,
+    ) -> Self {
         Self {
-            base_path: app_data_dir,
+            models,
+            language,
+            dictionary,
+            decoding,
+            vad,
         }
     }
 
+    pub fn models(&self) -> &ModelManager {
+        &self.models
+    }
+
+    /// Collects one recording session from `rx` and transcribes it, however long
+    /// it runs. With `keep_audio` also returns the session audio so it can be
+    /// archived (empty otherwise and on error), and always the model that
+    /// transcribed it. A session discarded in `sessions` ends without a final
+    /// pass and with an empty transcript.
     pub async fn start_processing_loop(
         &self,
-        rx: &mut mpsc::Receiver<SensitiveAudio>,
-        model_filename: &str,
+        rx: &mut AudioReceiver,
         app_handle: &AppHandle,
-    ) -> SensitiveTranscript {
-        let model_path = self.base_path.join(model_filename);
-
-        if !model_path.exists() {
-            println!("[ERROR] Whisper model not found at {:?}", model_path);
-            return SensitiveTranscript::new(format!(
-                "Error: AI model not found. Please download {} in settings.",
-                model_filename
-            ));
-        }
+        keep_audio: bool,
+        sessions: &Mutex<RecordingSessions>,
+    ) -> (SensitiveTranscript, SensitiveAudio, String) {
+        // Wait for the session to start before touching the model, so a model
+        // selected in between is the one that transcribes it
+        let Some(first) = rx.recv().await else {
+            return (
+                SensitiveTranscript::new(String::new()),
+                SensitiveAudio::new(Vec::new()),
+                String::new(),
+            );
+        };
+        // Its first chunk arrives while it records, before any later session starts
+        let session = sessions.lock().current();
 
-        let ctx = WhisperContext::new_with_params(
-            &model_path.to_string_lossy(),
-            WhisperContextParameters::default(),
-        )
-        .expect("failed to load model");
+        // Resident after the first dictation; loads (or swaps) only when needed
+        let (model_filename, ctx) = match self.models.acquire() {
+            Ok(model) => model,
+            Err(e) => {
+                println!("[ERROR] {}", e);
+                // Drain the session so its audio doesn't leak into the next one
+                while let Ok(Some(_)) =
+                    tokio::time::timeout(Duration::from_millis(200), rx.recv()).await
+                {}
+                return (
+                    SensitiveTranscript::new(format!("Error: {}", e)),
+                    SensitiveAudio::new(Vec::new()),
+                    self.models.selected(),
+                );
+            }
+        };
 
-        let mut state = ctx.create_state().expect("failed to create state");
+        let mut decoder = match ctx.create_state() {
+            Ok(state) => Decoder { ctx, state },
+            Err(e) => {
+                println!("[ERROR] Failed to create Whisper state: {}", e);
+                while let Ok(Some(_)) =
+                    tokio::time::timeout(Duration::from_millis(200), rx.recv()).await
+                {}
+                return (
+                    SensitiveTranscript::new(String::new()),
+                    SensitiveAudio::new(Vec::new()),
+                    model_filename,
+                );
+            }
+        };
 
+        // Picked once per session, for the app being dictated into
+        let mut language = self.session_language(&model_filename);
+        let vocabulary = self.session_vocabulary();
         println!(
-            "[DEBUG] Inference Loop Started for model: {}",
-            model_filename
+            "[DEBUG] Inference Loop Started for model: {} (language: {})",
+            model_filename, language
         );
 
-        let mut samples_buffer = Vec::new();
-        let mut full_transcript = String::new();
-        let chunk_limit = 16000 * 30; // Hard limit 30s to prevent RAM explosion
+        // Only the current segment's audio is kept (see `longform`), plus the
+        // whole session if it is going to be archived
+        let mut segment = first.as_slice().to_vec();
+        let mut session_audio = if keep_audio {
+            first.as_slice().to_vec()
+        } else {
+            Vec::new()
+        };
+        let aggressiveness = self.vad.lock().aggressiveness;
+        let mut pauses = PauseTracker::new(Box::new(WebRtcVad::new(aggressiveness)));
+        pauses.push(first.as_slice());
+        // The pipeline opens every session with the pre-roll (see audio.rs), which
+        // may hold speech from before the hotkey; it doesn't count as dictated
+        let pre_roll_speech = pauses.speech_samples();
+        drop(first);
+        // Session sample `segment` starts at, and samples received so far
+        let mut segment_start = 0;
+        let mut total = segment.len();
+        let mut stream = LocalAgreement::default();
+        // Decided when the first segment ends, then kept for the whole session
+        let mut translate = None;
+        let mut translation = Vec::new();
+        let mut result = None;
         let mut last_inference_time = Instant::now();
         let inference_interval = Duration::from_millis(300); // Snappier ghost text
 
@@ -69,29 +168,94 @@ impl InferenceEngine {
             // But we also want to process *while* receiving if enough time passed.
             match tokio::time::timeout(Duration::from_millis(200), rx.recv()).await {
                 Ok(Some(chunk)) => {
-                    samples_buffer.extend_from_slice(chunk.as_slice());
+                    let samples = chunk.as_slice();
+                    pauses.push(samples);
+                    segment.extend_from_slice(samples);
+                    total += samples.len();
+                    if keep_audio {
+                        let room = MAX_ARCHIVED_SAMPLES - session_audio.len();
+                        if samples.len() > room && room > 0 {
+                            println!("[WARNING] Session too long to archive in full; keeping its first 10 minutes.");
+                        }
+                        session_audio.extend_from_slice(&samples[..samples.len().min(room)]);
+                    }
+                    drop(chunk);
 
                     // Live Streaming / Ghost Text Logic
-                    if samples_buffer.len() > 3200
-                        && last_inference_time.elapsed() > inference_interval
+                    // (token end times can overshoot the audio Whisper was given)
+                    let offset = stream.window_start().clamp(segment_start, total);
+                    if total - offset > 3200 && last_inference_time.elapsed() > inference_interval
                     {
-                        // Run partial inference for Ghost Text
-                        // We clone the buffer to not block the collector?
-                        // Actually whisper runs on CPU, so it will block this thread.
-                        // Since this is inside `spawn`, it blocks only this async task.
-                        // `rx` buffer will hold incoming audio.
-
-                        let partial_text = self.run_inference(&mut state, &samples_buffer);
-                        // Emit Ghost Text
-                        let _ = app_handle.emit("transcript_partial", &partial_text);
-                        // println!("[DEBUG] Ghost: {}", partial_text); // customized logging
-                        last_inference_time = Instant::now();
-                    }
+                        // Only the uncommitted window is decoded, so a pass costs
+                        // the same late in a long utterance as at its start.
+                        // Whisper runs on CPU and blocks this async task meanwhile;
+                        // the hand-off queue grows, nothing is dropped.
+                        let (words, lang) = self.decode_window(
+                            &mut decoder,
+                            &segment[offset - segment_start..],
+                            offset,
+                            &language,
+                            Pass::Partial,
+                            &dictionary::initial_prompt(&vocabulary, &stream.prompt()),
+                        );
+                        // Windows are short: detecting on each one could switch
+                        // languages mid-sentence
+                        if language == language::AUTO && total >= PIN_LANGUAGE_SAMPLES {
+                            if let Some(lang) = lang {
+                                println!("[DEBUG] Detected language: {}", lang);
+                                language = lang;
+                            }
+                        }
+                        let mut partial = stream.insert(words);
 
-                    // Safety Clean
-                    if samples_buffer.len() > chunk_limit {
-                        println!("[WARN] Buffer overflow protection. Flushing.");
-                        break;
+                        // Long-form: end the segment at a pause before it outgrows
+                        // Whisper's 30 s, and let its audio go
+                        if let Some(cut) = longform::segment_cut(segment_start, total, &pauses)
+                        {
+                            let offset = stream.window_start().clamp(segment_start, cut);
+                            let words = if offset < cut {
+                                let end = (cut + longform::OVERLAP_SAMPLES).min(total);
+                                self.decode_window(
+                                    &mut decoder,
+                                    &segment[offset - segment_start..end - segment_start],
+                                    offset,
+                                    &language,
+                                    Pass::Final,
+                                    &dictionary::initial_prompt(&vocabulary, &stream.prompt()),
+                                )
+                                .0
+                            } else {
+                                Vec::new()
+                            };
+                            partial = stream.cut(words, cut);
+
+                            let done = cut - segment_start;
+                            let translating = *translate
+                                .get_or_insert_with(|| self.should_translate(&model_filename));
+                            if translating {
+                                let (segments, _) = self.run_inference(
+                                    &mut decoder,
+                                    &segment[..done],
+                                    segment_start,
+                                    &language,
+                                    Pass::Translate,
+                                    &vocabulary,
+                                );
+                                translation.extend(segments);
+                            }
+                            // Security: overwrite before releasing the samples
+                            segment[..done].iter_mut().for_each(|s| *s = 0.0);
+                            segment.drain(..done);
+                            segment_start = cut;
+                            pauses.forget_before(cut);
+                            println!(
+                                "[DEBUG] Long-form: segment ended at {:.1} s",
+                                cut as f32 / 16000.0
+                            );
+                        }
+
+                        let _ = app_handle.emit("transcript_partial", &partial);
+                        last_inference_time = Instant::now();
                     }
                 }
                 Ok(None) => {
@@ -101,52 +265,274 @@ impl InferenceEngine {
                     // Timeout = Silence detected (User stopped speaking for > 200ms)
                     // In "Always On" mode, audio.rs STOPS sending data when silence/unflagged.
                     // So this timeout means "Recording Session Ended".
-                    if !samples_buffer.is_empty() {
-                        println!("[DEBUG] Silence detected. Finalizing transcription...");
-                        let text = self.run_inference(&mut state, &samples_buffer);
-                        full_transcript.push_str(&text);
-
-                        // Clear Ghost Text on finish
-                        let _ = app_handle.emit("transcript_partial", "");
-
+                    if sessions.lock().is_discarded(session) {
+                        // Push-to-talk released before the minimum hold: an accidental tap
+                        println!("[DEBUG] Push-to-talk tap too short, session discarded.");
+                        let _ = app_handle.emit("transcript_partial", PartialTranscript::default());
+                        let _ = app_handle.emit("status", "Ready");
                         break;
                     }
+                    println!("[DEBUG] Silence detected. Finalizing transcription...");
+                    // Ghost text stays in the spoken language; only the final passes translate
+                    let translating =
+                        *translate.get_or_insert_with(|| self.should_translate(&model_filename));
+                    let mut words = Vec::new();
+                    let lang = if translating {
+                        // Translation needs whole segments, not the streamed words
+                        let (segments, lang) = self.run_inference(
+                            &mut decoder,
+                            &segment,
+                            segment_start,
+                            &language,
+                            Pass::Translate,
+                            &vocabulary,
+                        );
+                        translation.extend(segments);
+                        lang
+                    } else {
+                        let offset = stream.window_start().clamp(segment_start, total);
+                        let (last, lang) = if offset < total {
+                            let (words, lang) = self.decode_window(
+                                &mut decoder,
+                                &segment[offset - segment_start..],
+                                offset,
+                                &language,
+                                Pass::Final,
+                                &dictionary::initial_prompt(&vocabulary, &stream.prompt()),
+                            );
+                            (Some(words), lang)
+                        } else {
+                            (None, None)
+                        };
+                        words = std::mem::take(&mut stream).finish(last);
+                        lang
+                    };
+                    let detected =
+                        lang.or_else(|| Some(language.clone()).filter(|l| l != language::AUTO));
+                    let speech =
+                        transcript::speech_ms(pauses.speech_samples() - pre_roll_speech);
+                    result = Some(if translating {
+                        SensitiveTranscript::from_segments(
+                            std::mem::take(&mut translation),
+                            detected,
+                            true,
+                            Some(speech),
+                        )
+                    } else {
+                        SensitiveTranscript::from_words(words, detected, false, Some(speech))
+                    });
+
+                    // Clear Ghost Text on finish
+                    let _ = app_handle.emit("transcript_partial", PartialTranscript::default());
+
+                    break;
                 }
             }
         }
+        // Security: overwrite before releasing the samples
+        segment.iter_mut().for_each(|s| *s = 0.0);
 
+        let transcript = result.unwrap_or_else(|| SensitiveTranscript::new(String::new()));
         println!(
-            "[DEBUG] Whisper Final Result: \"{}\"",
-            full_transcript.trim()
+            "[DEBUG] Whisper Final Result ({}, {:.1} s, confidence {:.2}, speech {} ms): \"{}\"",
+            transcript.language().unwrap_or("?"),
+            total as f32 / 16000.0,
+            transcript.confidence().unwrap_or(0.0),
+            transcript.speech_ms().unwrap_or(0),
+            transcript.as_str()
         );
-        SensitiveTranscript::new(full_transcript.trim().to_string())
+        (
+            transcript,
+            SensitiveAudio::new(session_audio),
+            model_filename,
+        )
     }
 
-    fn run_inference(&self, state: &mut whisper_rs::WhisperState, samples: &[f32]) -> String {
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_language(Some("en"));
-        params.set_print_special(false);
-        params.set_print_progress(false);
-        params.set_print_realtime(false);
-        params.set_print_timestamps(false);
-        
-        // Performance Optimization: Use multiple threads
-        // We have 16 cores, let's use 6-8 for speed without lagging the UI
-        let threads = std::cmp::min(8, num_cpus::get() as i32);
-        params.set_n_threads(threads);
+    /// One-shot transcription of already captured 16 kHz audio (e.g. an archived session).
+    pub fn transcribe(
+        &self,
+        model_filename: &str,
+        samples: &[f32],
+    ) -> Result<SensitiveTranscript, String> {
+        let ctx = self.models.acquire_model(model_filename)?;
+        let state = ctx.create_state().map_err(|e| e.to_string())?;
+        let mut decoder = Decoder { ctx, state };
+
+        let language = self.session_language(model_filename);
+        let translate = self.should_translate(model_filename);
+        let vocabulary = self.session_vocabulary();
+        let pass = if translate { Pass::Translate } else { Pass::Final };
+        let (segments, detected) =
+            self.run_inference(&mut decoder, samples, 0, &language, pass, &vocabulary);
+        let aggressiveness = self.vad.lock().aggressiveness;
+        let mut pauses = PauseTracker::new(Box::new(WebRtcVad::new(aggressiveness)));
+        pauses.push(samples);
+        Ok(SensitiveTranscript::from_segments(
+            segments,
+            detected,
+            translate,
+            // Already captured: no hotkey to count from, so all of it counts
+            Some(transcript::speech_ms(pauses.speech_samples())),
+        ))
+    }
+
+    // Configured language for the active app, limited to what the model supports
+    fn session_language(&self, model_filename: &str) -> String {
+        let app_name = OSIntegration::get_active_app_name();
+        let settings = self.language.lock();
+        let requested = settings.language_for(&app_name);
+        let language = language::effective_language(model_filename, requested);
+        if language != requested {
+            println!(
+                "[WARNING] {} is English-only; transcribing as \"en\" instead of \"{}\". Download a multilingual model for other languages.",
+                model_filename, requested
+            );
+        }
+        language.to_string()
+    }
+
+    // Dictionary words for the active app and its context mode
+    fn session_vocabulary(&self) -> String {
+        let context = ContextEngine::get_context();
+        self.dictionary
+            .lock()
+            .vocabulary(&context.app_name, &context.mode)
+    }
+
+    // Translate toggle, read when the first segment (or the whole dictation) ends,
+    // so it can still be flipped while dictating
+    fn should_translate(&self, model_filename: &str) -> bool {
+        if !self.language.lock().translate {
+            return false;
+        }
+        if language::is_english_only(model_filename) {
+            println!(
+                "[WARNING] {} is English-only and can't translate. Download a multilingual model.",
+                model_filename
+            );
+            return false;
+        }
+        true
+    }
 
+    /// Whisper's segments as words, and the language that was spoken. `offset`
+    /// is the first sample's position in the session; `prompt` the initial
+    /// prompt (see `dictionary::initial_prompt`). A `Translate` pass returns
+    /// Whisper's English translation.
+    fn run_inference(
+        &self,
+        decoder: &mut Decoder,
+        samples: &[f32],
+        offset: usize,
+        language: &str,
+        pass: Pass,
+        prompt: &str,
+    ) -> (Vec<Vec<Word>>, Option<String>) {
+        let profile = {
+            let decoding = self.decoding.lock();
+            match pass {
+                Pass::Partial => decoding.partial.clone(),
+                Pass::Final | Pass::Translate => decoding.final_pass.clone(),
+            }
+        };
+        let mut params = Self::params(&profile, language, pass == Pass::Translate);
+        params.set_token_timestamps(true);
+        if !prompt.is_empty() {
+            params.set_initial_prompt(prompt);
+        }
+        let Decoder { ctx, state } = decoder;
         if let Err(e) = state.full(params, samples) {
             println!("[ERROR] Whisper Inference Failed: {}", e);
-            return String::new();
+            return (Vec::new(), None);
         }
 
-        let mut result = String::new();
+        // Token times are in 10 ms steps from the first sample
+        let to_sample = |t: i64| offset + t.max(0) as usize * 160;
+        let mut segments = Vec::new();
         let num_segments = state.full_n_segments().unwrap_or(0);
-        for i in 0..num_segments {
-            if let Ok(segment) = state.full_get_segment_text(i) {
-                result.push_str(&segment);
+        for seg in 0..num_segments {
+            let mut words = WordBuilder::default();
+            let num_tokens = state.full_n_tokens(seg).unwrap_or(0);
+            for tok in 0..num_tokens {
+                // Bytes, not text: a token can end inside a UTF-8 character
+                let Some((data, bytes)) = state
+                    .full_get_token_data(seg, tok)
+                    .ok()
+                    .and_then(|d| Some((d, ctx.token_to_cstr(d.id).ok()?.to_bytes())))
+                else {
+                    continue;
+                };
+                // Timestamp and control tokens, e.g. "[_BEG_]" or "<|endoftext|>"
+                if bytes.starts_with(b"[_") || bytes.starts_with(b"<|") {
+                    continue;
+                }
+                words.push_token(bytes, to_sample(data.t0), to_sample(data.t1), data.p);
             }
+            segments.push(words.finish());
         }
-        result
+        (segments, Self::detected_language(state))
+    }
+
+    /// Decodes one streaming window into words. `offset` is the window's first
+    /// sample in the session; `prompt` the vocabulary and committed text before it.
+    fn decode_window(
+        &self,
+        decoder: &mut Decoder,
+        window: &[f32],
+        offset: usize,
+        language: &str,
+        pass: Pass,
+        prompt: &str,
+    ) -> (Vec<Word>, Option<String>) {
+        let (segments, language) =
+            self.run_inference(decoder, window, offset, language, pass, prompt);
+        (segments.into_iter().flatten().collect(), language)
+    }
+
+    fn params<'a>(
+        profile: &DecodingProfile,
+        language: &'a str,
+        translate: bool,
+    ) -> FullParams<'a, 'a> {
+        let strategy = match profile.strategy {
+            Strategy::Greedy { best_of } => SamplingStrategy::Greedy {
+                best_of: best_of as i32,
+            },
+            // whisper.cpp doesn't implement patience; -1 is its default
+            Strategy::BeamSearch { beam_size } => SamplingStrategy::BeamSearch {
+                beam_size: beam_size as i32,
+                patience: -1.0,
+            },
+        };
+        let mut params = FullParams::new(strategy);
+        params.set_temperature(profile.temperature);
+        params.set_temperature_inc(profile.temperature_increment);
+        params.set_entropy_thold(profile.entropy_threshold);
+        params.set_logprob_thold(profile.logprob_threshold);
+        params.set_no_speech_thold(profile.no_speech_threshold);
+        // "auto" makes Whisper detect the language from the audio first
+        params.set_language(Some(language));
+        params.set_translate(translate);
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+
+        // Performance Optimization: Use multiple threads
+        // Unless configured, 6-8 for speed without lagging the UI
+        let threads = match profile.threads {
+            0 => std::cmp::min(8, num_cpus::get() as i32),
+            n => n as i32,
+        };
+        params.set_n_threads(threads);
+        params
+    }
+
+    fn detected_language(state: &WhisperState) -> Option<String> {
+        state
+            .full_lang_id_from_state()
+            .ok()
+            .and_then(whisper_rs::get_lang_str)
+            .map(str::to_string)
     }
 }
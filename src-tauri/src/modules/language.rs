@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Lets Whisper detect the spoken language instead of assuming one.
+pub const AUTO: &str = "auto";
+
+/// Transcription language, persisted as `"language"` in config.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LanguageSettings {
+    /// Whisper language code (`"en"`, `"de"`, ...) or `"auto"`.
+    pub language: String,
+    /// Checked in order; the first rule whose app matches wins.
+    pub rules: Vec<LanguageRule>,
+    /// Translate the final pass to English (multilingual models only).
+    pub translate: bool,
+}
+
+impl Default for LanguageSettings {
+    fn default() -> Self {
+        Self {
+            language: "en".to_string(),
+            rules: Vec::new(),
+            translate: false,
+        }
+    }
+}
+
+/// Dictating into an app whose name contains `app` (case-insensitive, like the
+/// refinement modes) uses `language` instead of the default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageRule {
+    pub app: String,
+    pub language: String,
+}
+
+impl LanguageSettings {
+    /// Language for a dictation into `app_name`.
+    pub fn language_for(&self, app_name: &str) -> &str {
+        let app_lower = app_name.to_lowercase();
+        self.rules
+            .iter()
+            .find(|r| !r.app.trim().is_empty() && app_lower.contains(&r.app.trim().to_lowercase()))
+            .map(|r| r.language.as_str())
+            .unwrap_or(&self.language)
+    }
+}
+
+/// `.en` models only know English; detection and other languages produce garbage.
+pub fn is_english_only(model_filename: &str) -> bool {
+    model_filename.contains(".en.")
+}
+
+/// What `model_filename` can actually be asked for when `requested` is wanted.
+pub fn effective_language<'a>(model_filename: &str, requested: &'a str) -> &'a str {
+    if is_english_only(model_filename) && requested != "en" {
+        "en"
+    } else {
+        requested
+    }
+}
@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+
+// Runs on the 16 kHz mono stream (after resampling), except the input
+// detectors which look at the raw device samples
+const SAMPLE_RATE: f32 = 16000.0;
+// AGC time constants: turn down fast on loud input, come back up slowly
+const ATTACK_SECONDS: f32 = 0.05;
+const RELEASE_SECONDS: f32 = 1.5;
+// Blocks quieter than this are room noise: hold the gain instead of pumping it up
+const NOISE_GATE_DBFS: f32 = -55.0;
+const MIN_GAIN_DB: f32 = -20.0;
+// Soft limiter knee, keeps the gained signal below full scale
+const LIMITER_THRESHOLD: f32 = 0.9;
+// Too-quiet is judged after this much recording
+const QUIET_GRACE_SECONDS: f32 = 2.0;
+// More than this share of clipped input samples (over the window) is a warning
+const CLIP_RATIO_WARNING: f32 = 0.001;
+const CLIP_WINDOW_SECONDS: f32 = 1.0;
+
+/// Gain and level settings, persisted as `"gain"` in config.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GainSettings {
+    /// Automatic gain control on the 16 kHz stream before it reaches the ring buffer.
+    /// Off by default, so an update doesn't suddenly boost existing setups.
+    pub agc: bool,
+    /// Level the AGC steers speech towards.
+    pub target_dbfs: f32,
+    /// Upper bound for the AGC boost.
+    pub max_gain_db: f32,
+    /// Raw input samples at or above this magnitude count as clipped.
+    pub clip_threshold: f32,
+    /// A recording whose loudest block stays below this is reported as too quiet.
+    pub quiet_dbfs: f32,
+}
+
+impl Default for GainSettings {
+    fn default() -> Self {
+        Self {
+            agc: false,
+            target_dbfs: -20.0,
+            max_gain_db: 24.0,
+            clip_threshold: 0.99,
+            quiet_dbfs: -45.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelWarning {
+    Clipping,
+    TooQuiet,
+}
+
+/// Input levels of one recording, measured on the raw device signal.
+/// Emitted as `"level_stats"` when the recording ends.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LevelStats {
+    pub duration_ms: u64,
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+    /// Loudest 10 ms-ish block, a rough "speech level".
+    pub max_block_rms_dbfs: f32,
+    pub clipped_samples: u64,
+    pub clipped_ratio: f32,
+    /// Average AGC gain applied during the recording.
+    pub avg_gain_db: f32,
+    pub warnings: Vec<LevelWarning>,
+}
+
+/// What the pipeline reports to the UI loop in main.rs, which emits and clears it.
+#[derive(Debug, Default)]
+pub struct LevelReport {
+    pub warnings: Vec<LevelWarning>,
+    /// Stats of a recording that just ended, not yet emitted.
+    pub finished: Option<LevelStats>,
+    pub last_session: Option<LevelStats>,
+}
+
+/// Root mean square of a block, 0 for an empty one.
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|&x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+pub fn to_dbfs(linear: f32) -> f32 {
+    20.0 * linear.max(1e-9).log10()
+}
+
+fn from_db(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// AGC plus clipping/too-quiet detection for one stream.
+pub struct LevelMonitor {
+    gain_db: f32,
+    gain: f32,
+    // Per-session accumulators (raw input)
+    recording: bool,
+    input_samples: u64,
+    sum_squares: f64,
+    peak: f32,
+    max_block_rms: f32,
+    clipped: u64,
+    gain_db_sum: f64,
+    gain_blocks: u64,
+    output_samples: u64,
+    warnings: Vec<LevelWarning>,
+    // Current clip window (raw input samples)
+    window_samples: u64,
+    window_clipped: u64,
+}
+
+impl Default for LevelMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LevelMonitor {
+    pub fn new() -> Self {
+        Self {
+            gain_db: 0.0,
+            gain: 1.0,
+            recording: false,
+            input_samples: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+            max_block_rms: 0.0,
+            clipped: 0,
+            gain_db_sum: 0.0,
+            gain_blocks: 0,
+            output_samples: 0,
+            warnings: Vec::new(),
+            window_samples: 0,
+            window_clipped: 0,
+        }
+    }
+
+    /// Clears the session accumulators. The AGC gain is kept so a new
+    /// recording starts at the level the stream has already settled on.
+    pub fn start_session(&mut self) {
+        let (gain_db, gain) = (self.gain_db, self.gain);
+        *self = Self::new();
+        self.gain_db = gain_db;
+        self.gain = gain;
+        self.recording = true;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn finish_session(&mut self) -> LevelStats {
+        self.recording = false;
+        let rms = if self.input_samples > 0 {
+            (self.sum_squares / self.input_samples as f64).sqrt() as f32
+        } else {
+            0.0
+        };
+        LevelStats {
+            duration_ms: self.output_samples * 1000 / SAMPLE_RATE as u64,
+            peak_dbfs: to_dbfs(self.peak),
+            rms_dbfs: to_dbfs(rms),
+            max_block_rms_dbfs: to_dbfs(self.max_block_rms),
+            clipped_samples: self.clipped,
+            clipped_ratio: if self.input_samples > 0 {
+                self.clipped as f32 / self.input_samples as f32
+            } else {
+                0.0
+            },
+            avg_gain_db: if self.gain_blocks > 0 {
+                (self.gain_db_sum / self.gain_blocks as f64) as f32
+            } else {
+                0.0
+            },
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Looks at a raw (interleaved, device rate) block. `rms` is the block RMS the
+    /// pipeline already computed for the `amplitude` meter; `samples_per_second` is
+    /// rate * channels. Returns a warning the first time a condition is met in the
+    /// current recording.
+    pub fn observe_input(
+        &mut self,
+        data: &[f32],
+        rms: f32,
+        samples_per_second: f32,
+        settings: &GainSettings,
+    ) -> Option<LevelWarning> {
+        if !self.recording || data.is_empty() {
+            return None;
+        }
+
+        let clipped = data
+            .iter()
+            .filter(|x| x.abs() >= settings.clip_threshold)
+            .count() as u64;
+        let block_peak = data.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+
+        self.input_samples += data.len() as u64;
+        self.sum_squares += (rms as f64).powi(2) * data.len() as f64;
+        self.peak = self.peak.max(block_peak);
+        self.max_block_rms = self.max_block_rms.max(rms);
+        self.clipped += clipped;
+
+        // Clipping: judged per window of about a second of input
+        self.window_samples += data.len() as u64;
+        self.window_clipped += clipped;
+        if self.window_samples as f32 >= samples_per_second * CLIP_WINDOW_SECONDS {
+            let too_many = self.window_clipped as f32 > self.window_samples as f32 * CLIP_RATIO_WARNING;
+            self.window_samples = 0;
+            self.window_clipped = 0;
+            if too_many {
+                if let Some(w) = self.warn(LevelWarning::Clipping) {
+                    return Some(w);
+                }
+            }
+        }
+
+        // Too quiet: nothing loud enough after the grace period
+        let elapsed = self.input_samples as f32 / samples_per_second;
+        if elapsed >= QUIET_GRACE_SECONDS && to_dbfs(self.max_block_rms) < settings.quiet_dbfs {
+            return self.warn(LevelWarning::TooQuiet);
+        }
+        None
+    }
+
+    /// AGC on the 16 kHz mono stream, in place. Runs whether or not we are
+    /// recording so the gain has settled when the hotkey is pressed.
+    pub fn apply_gain(&mut self, samples: &mut [f32], settings: &GainSettings) {
+        if samples.is_empty() {
+            return;
+        }
+        if self.recording {
+            self.output_samples += samples.len() as u64;
+        }
+        if !settings.agc {
+            self.gain_db = 0.0;
+            self.gain = 1.0;
+            return;
+        }
+
+        let level_db = to_dbfs(rms(samples));
+
+        if level_db > NOISE_GATE_DBFS {
+            let desired = (settings.target_dbfs - level_db).clamp(MIN_GAIN_DB, settings.max_gain_db);
+            let tau = if desired < self.gain_db {
+                ATTACK_SECONDS
+            } else {
+                RELEASE_SECONDS
+            };
+            let alpha = 1.0 - (-(samples.len() as f32) / (tau * SAMPLE_RATE)).exp();
+            self.gain_db += (desired - self.gain_db) * alpha;
+        }
+        self.gain_db = self.gain_db.min(settings.max_gain_db);
+
+        // Ramp across the block to avoid zipper noise
+        let start = self.gain;
+        let end = from_db(self.gain_db);
+        let step = (end - start) / samples.len() as f32;
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = soft_limit(*sample * (start + step * (i + 1) as f32));
+        }
+        self.gain = end;
+
+        if self.recording {
+            self.gain_db_sum += self.gain_db as f64;
+            self.gain_blocks += 1;
+        }
+    }
+
+    fn warn(&mut self, warning: LevelWarning) -> Option<LevelWarning> {
+        if self.warnings.contains(&warning) {
+            return None;
+        }
+        self.warnings.push(warning);
+        Some(warning)
+    }
+}
+
+// Linear below the knee, tanh-shaped above it, never reaches full scale
+fn soft_limit(x: f32) -> f32 {
+    let magnitude = x.abs();
+    if magnitude <= LIMITER_THRESHOLD {
+        return x;
+    }
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    let limited = LIMITER_THRESHOLD + headroom * ((magnitude - LIMITER_THRESHOLD) / headroom).tanh();
+    limited.copysign(x)
+}
@@ -1,4 +1,4 @@
-use crate::modules::inference::SensitiveTranscript;
+use crate::modules::transcript::SensitiveTranscript;
 use crate::modules::os_integration::OSIntegration;
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
@@ -19,7 +19,7 @@ struct OllamaResponse {
     response: String,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum AppMode {
     Coding,
     Chat,
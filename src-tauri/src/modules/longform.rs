@@ -0,0 +1,114 @@
+use crate::modules::vad::VoiceActivityDetector;
+use std::collections::VecDeque;
+
+// Sessions of any length: Whisper takes at most 30 s per pass, so the session is
+// split into segments that end at pauses in speech. Only the current segment's
+// audio is kept; a finished segment is decoded a final time with a little
+// overlap into the next one, and words starting after the cut are left to the
+// next segment.
+
+const SAMPLE_RATE: usize = 16000;
+/// Segments shorter than this are not cut.
+pub const MIN_SEGMENT_SAMPLES: usize = SAMPLE_RATE * 5;
+/// From this length on, the segment ends at the next pause.
+pub const SOFT_SEGMENT_SAMPLES: usize = SAMPLE_RATE * 15;
+/// Without a pause the segment is cut here anyway, leaving room for the overlap.
+pub const HARD_SEGMENT_SAMPLES: usize = SAMPLE_RATE * 25;
+/// Audio after the cut included in the segment's final pass, so a word that
+/// crosses the cut is decoded whole.
+pub const OVERLAP_SAMPLES: usize = SAMPLE_RATE;
+// Gaps shorter than this are between words, not a pause
+const MIN_PAUSE_SAMPLES: usize = SAMPLE_RATE * 3 / 10;
+
+/// Finds pauses in the session audio with a VAD.
+pub struct PauseTracker {
+    vad: Box<dyn VoiceActivityDetector>,
+    frame: Vec<f32>,
+    // Session samples classified so far, and how many of them were speech
+    position: usize,
+    speech: usize,
+    // Start of the current run of non-speech frames
+    silent_since: Option<usize>,
+    // Middle of each finished pause, oldest first
+    pauses: VecDeque<usize>,
+}
+
+impl PauseTracker {
+    pub fn new(mut vad: Box<dyn VoiceActivityDetector>) -> Self {
+        vad.reset();
+        Self {
+            vad,
+            frame: Vec::new(),
+            position: 0,
+            speech: 0,
+            silent_since: None,
+            pauses: VecDeque::new(),
+        }
+    }
+
+    /// Feeds the next 16 kHz mono samples of the session.
+    pub fn push(&mut self, samples: &[f32]) {
+        let frame_size = self.vad.frame_size();
+        for &sample in samples {
+            self.frame.push(sample);
+            if self.frame.len() < frame_size {
+                continue;
+            }
+            let voiced = self.vad.is_speech(&self.frame);
+            self.frame.clear();
+            let frame_start = self.position;
+            self.position += frame_size;
+            if voiced {
+                self.speech += frame_size;
+            }
+
+            match (voiced, self.silent_since) {
+                (false, None) => self.silent_since = Some(frame_start),
+                (true, Some(since)) => {
+                    self.silent_since = None;
+                    if frame_start - since >= MIN_PAUSE_SAMPLES {
+                        self.pauses.push_back((since + frame_start) / 2);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Middle of the latest pause in `from..to`, including one still going on.
+    pub fn last_pause(&self, from: usize, to: usize) -> Option<usize> {
+        let ongoing = self
+            .silent_since
+            .filter(|&since| self.position - since >= MIN_PAUSE_SAMPLES)
+            .map(|since| (since + self.position) / 2);
+        ongoing
+            .into_iter()
+            .chain(self.pauses.iter().rev().copied())
+            .find(|&p| p >= from && p < to)
+    }
+
+    /// Samples classified as speech so far.
+    pub fn speech_samples(&self) -> usize {
+        self.speech
+    }
+
+    /// Drops pauses before `sample` once their segment is done.
+    pub fn forget_before(&mut self, sample: usize) {
+        while self.pauses.front().is_some_and(|&p| p < sample) {
+            self.pauses.pop_front();
+        }
+    }
+}
+
+/// Where to end the segment that started at `segment_start`, with `total`
+/// samples received so far, if it is time to end it.
+pub fn segment_cut(segment_start: usize, total: usize, pauses: &PauseTracker) -> Option<usize> {
+    let len = total.saturating_sub(segment_start);
+    if len < SOFT_SEGMENT_SAMPLES {
+        return None;
+    }
+    if let Some(pause) = pauses.last_pause(segment_start + MIN_SEGMENT_SAMPLES, total) {
+        return Some(pause);
+    }
+    (len >= HARD_SEGMENT_SAMPLES).then(|| total - OVERLAP_SAMPLES)
+}
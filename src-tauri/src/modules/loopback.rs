@@ -0,0 +1,375 @@
+use crate::modules::devices::{DeviceInfo, DeviceKind};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+// System audio ("loopback") on Linux: PulseAudio and PipeWire (via pipewire-pulse)
+// expose every output as a "<sink>.monitor" source. ALSA/cpal only sees the
+// generic "pulse" device, which can't be pointed at a source per stream, so
+// monitors are recorded with `parec` (from the same package as `pactl`), which
+// takes the source by name and has the server convert to 16 kHz mono.
+
+/// Device ids of monitor sources, next to the cpal ids of microphones.
+pub const LOOPBACK_ID_PREFIX: &str = "loopback:";
+/// Rate `MonitorCapture` delivers (mono f32).
+pub const MONITOR_SAMPLE_RATE: u32 = 16000;
+// 20 ms blocks
+const MONITOR_BLOCK_BYTES: usize = MONITOR_SAMPLE_RATE as usize / 50 * 4;
+// The mic may fall this far behind the system-audio stream (clock drift,
+// callback jitter) before the oldest system audio is dropped
+const MAX_LEAD_SAMPLES: usize = 16000 / 4;
+
+/// Mic + system-audio mix, persisted as `"loopback"` in config.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixSettings {
+    /// Mix system audio into the selected microphone.
+    pub enabled: bool,
+    /// Loopback device id to mix in; `None` = monitor of the default output.
+    pub monitor: Option<String>,
+    pub mic_gain_db: f32,
+    pub system_gain_db: f32,
+}
+
+impl Default for MixSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            monitor: None,
+            mic_gain_db: 0.0,
+            // Playback is usually mastered much louder than a mic at speaking distance
+            system_gain_db: -6.0,
+        }
+    }
+}
+
+/// A monitor source as listed by `pactl list sources`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorSource {
+    pub name: String,
+    pub description: String,
+    pub format: String,
+    pub channels: u16,
+    pub rate: u32,
+}
+
+/// The monitor source behind a loopback device id.
+pub fn monitor_name(device_id: &str) -> Option<&str> {
+    device_id.strip_prefix(LOOPBACK_ID_PREFIX)
+}
+
+pub fn device_info(source: &MonitorSource, default_sink: Option<&str>) -> DeviceInfo {
+    DeviceInfo {
+        id: format!("{}{}", LOOPBACK_ID_PREFIX, source.name),
+        raw_name: source.name.clone(),
+        display_name: format!(
+            "System audio ({})",
+            source.description.trim_start_matches("Monitor of ")
+        ),
+        host: "PulseAudio".to_string(),
+        channels: vec![source.channels],
+        stream_channels: source.channels,
+        sample_rates: vec![source.rate],
+        sample_formats: vec![source.format.clone()],
+        is_default: default_sink.map(|s| format!("{}.monitor", s)).as_deref()
+            == Some(source.name.as_str()),
+        kind: DeviceKind::Loopback,
+    }
+}
+
+/// Descriptor for an opened monitor, even if pactl can no longer list it.
+pub fn loopback_device(monitor: &str) -> DeviceInfo {
+    list_loopback_devices()
+        .into_iter()
+        .find(|d| d.raw_name == monitor)
+        .unwrap_or_else(|| {
+            let source = MonitorSource {
+                name: monitor.to_string(),
+                description: monitor.to_string(),
+                format: String::new(),
+                channels: 2,
+                rate: 48000,
+            };
+            device_info(&source, None)
+        })
+}
+
+/// Monitor sources from `LC_ALL=C pactl list sources`.
+pub fn parse_sources(output: &str) -> Vec<MonitorSource> {
+    let mut sources = Vec::new();
+    let mut current: Option<MonitorSource> = None;
+
+    for line in output.lines() {
+        if line.starts_with("Source #") {
+            sources.extend(current.take());
+            current = Some(MonitorSource {
+                name: String::new(),
+                description: String::new(),
+                format: String::new(),
+                channels: 2,
+                rate: 48000,
+            });
+            continue;
+        }
+        let Some(source) = current.as_mut() else {
+            continue;
+        };
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Name: ") {
+            source.name = name.to_string();
+        } else if let Some(description) = line.strip_prefix("Description: ") {
+            source.description = description.to_string();
+        } else if let Some(spec) = line.strip_prefix("Sample Specification: ") {
+            // e.g. "s16le 2ch 44100Hz"
+            for field in spec.split_whitespace() {
+                if let Some(ch) = field.strip_suffix("ch").and_then(|v| v.parse().ok()) {
+                    source.channels = ch;
+                } else if let Some(rate) = field.strip_suffix("Hz").and_then(|v| v.parse().ok()) {
+                    source.rate = rate;
+                } else {
+                    source.format = field.to_string();
+                }
+            }
+        }
+    }
+    sources.extend(current);
+    sources.retain(|s| s.name.ends_with(".monitor"));
+    for source in &mut sources {
+        if source.description.is_empty() {
+            source.description = source.name.clone();
+        }
+    }
+    sources
+}
+
+/// Default output from `LC_ALL=C pactl info`.
+pub fn parse_default_sink(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Default Sink: "))
+        .map(str::to_string)
+}
+
+#[cfg(target_os = "linux")]
+fn pactl(args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new("pactl")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// System-audio devices for the picker. Empty without PulseAudio/PipeWire (or off Linux).
+pub fn list_loopback_devices() -> Vec<DeviceInfo> {
+    #[cfg(target_os = "linux")]
+    {
+        let Some(list) = pactl(&["list", "sources"]) else {
+            println!("[DEBUG] pactl not available, no system audio sources.");
+            return Vec::new();
+        };
+        let default_sink = pactl(&["info"]).and_then(|info| parse_default_sink(&info));
+        parse_sources(&list)
+            .iter()
+            .map(|s| device_info(s, default_sink.as_deref()))
+            .collect()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Monitor of the default output, for `MixSettings::monitor == None`.
+pub fn default_monitor() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        pactl(&["info"])
+            .and_then(|info| parse_default_sink(&info))
+            .map(|sink| format!("{}.monitor", sink))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// A `parec` recording of one monitor source. Dropping it stops the recording.
+pub struct MonitorCapture {
+    child: Child,
+    stopping: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl MonitorCapture {
+    /// Records `monitor` (a source name) and hands 16 kHz mono blocks to `on_data`
+    /// on a reader thread. `on_end` runs if the recording stops on its own (the
+    /// source went away, the sound server restarted).
+    pub fn start(
+        monitor: &str,
+        mut on_data: impl FnMut(&[f32]) + Send + 'static,
+        on_end: impl FnOnce() + Send + 'static,
+    ) -> Result<Self> {
+        let mut child = Command::new("parec")
+            .arg(format!("--device={}", monitor))
+            .args(["--format=float32le", "--channels=1", "--raw", "--latency-msec=20"])
+            .arg(format!("--rate={}", MONITOR_SAMPLE_RATE))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("Could not start parec to record {}: {}", monitor, e))?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("parec has no output"))?;
+
+        let stopping = Arc::new(AtomicBool::new(false));
+        let stopped = stopping.clone();
+        let reader = std::thread::Builder::new()
+            .name("vibeflow-monitor".to_string())
+            .spawn(move || {
+                let mut buf = vec![0u8; MONITOR_BLOCK_BYTES];
+                let mut samples = Vec::with_capacity(MONITOR_BLOCK_BYTES / 4);
+                let mut filled = 0;
+                loop {
+                    // EOF: parec exited or was killed
+                    let n = match stdout.read(&mut buf[filled..]) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => n,
+                    };
+                    filled += n;
+                    // Only whole samples, keep the remainder
+                    let usable = filled - filled % 4;
+                    samples.clear();
+                    samples.extend(
+                        buf[..usable]
+                            .chunks_exact(4)
+                            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                    );
+                    if !samples.is_empty() {
+                        on_data(&samples);
+                    }
+                    buf.copy_within(usable..filled, 0);
+                    filled -= usable;
+                }
+                if !stopped.load(Ordering::Relaxed) {
+                    on_end();
+                }
+            })?;
+
+        Ok(Self {
+            child,
+            stopping,
+            reader: Some(reader),
+        })
+    }
+}
+
+impl Drop for MonitorCapture {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Records the system-audio monitor into `bus` for the mic + system mix.
+/// `monitor` is a loopback device id, `None` = monitor of the default output.
+pub fn start_mix_feed(bus: &Arc<MixBus>, monitor: Option<&str>) -> Result<MonitorCapture> {
+    let monitor = monitor
+        .and_then(monitor_name)
+        .map(str::to_string)
+        .or_else(default_monitor)
+        .ok_or_else(|| anyhow!("No system audio source found (needs PulseAudio or PipeWire)"))?;
+
+    // Keeps the bus marked as fed for as long as the recording (and its reader) lives
+    let guard = bus.attach();
+    let feed = bus.clone();
+    let capture = MonitorCapture::start(
+        &monitor,
+        move |data| {
+            let _ = &guard;
+            feed.push(data);
+        },
+        || println!("[WARNING] System audio recording ended."),
+    )?;
+    println!("[DEBUG] Mixing in system audio from {}", monitor);
+    Ok(capture)
+}
+
+/// Hand-over of system audio (16 kHz mono) from the monitor stream to the
+/// microphone pipeline, which mixes it in block by block.
+#[derive(Default)]
+pub struct MixBus {
+    fifo: Mutex<VecDeque<f32>>,
+    // Set while a monitor stream feeds the bus
+    active: AtomicBool,
+}
+
+impl MixBus {
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Marks the bus as fed until the returned guard is dropped (together with
+    /// the monitor recording's reader that owns it).
+    pub fn attach(self: &Arc<Self>) -> MixFeedGuard {
+        self.fifo.lock().clear();
+        self.active.store(true, Ordering::Relaxed);
+        MixFeedGuard { bus: self.clone() }
+    }
+
+    /// Called from the monitor stream with resampled 16 kHz mono audio.
+    pub fn push(&self, samples: &[f32]) {
+        let mut fifo = self.fifo.lock();
+        fifo.extend(samples.iter().copied());
+        let excess = fifo.len().saturating_sub(MAX_LEAD_SAMPLES);
+        fifo.drain(..excess);
+    }
+
+    /// Mixes as much system audio as `chunk` is long into it, applying both gains.
+    /// Missing system audio (stream starting up, underrun) counts as silence.
+    pub fn mix_into(&self, chunk: &mut [f32], settings: &MixSettings) {
+        if !self.is_active() {
+            return;
+        }
+        let mic_gain = from_db(settings.mic_gain_db);
+        let system_gain = from_db(settings.system_gain_db);
+        let mut fifo = self.fifo.lock();
+        for sample in chunk.iter_mut() {
+            let system = fifo.pop_front().unwrap_or(0.0);
+            *sample = (*sample * mic_gain + system * system_gain).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+pub struct MixFeedGuard {
+    bus: Arc<MixBus>,
+}
+
+impl Drop for MixFeedGuard {
+    fn drop(&mut self) {
+        self.bus.active.store(false, Ordering::Relaxed);
+        let mut fifo = self.bus.fifo.lock();
+        // Security: overwrite before releasing
+        fifo.iter_mut().for_each(|s| *s = 0.0);
+        fifo.clear();
+    }
+}
+
+fn from_db(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
@@ -0,0 +1,160 @@
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+use serde::Serialize;
+use std::sync::Arc;
+
+// Runs on the resampled 16 kHz mono stream
+const SAMPLE_RATE: f32 = 16000.0;
+// 512-point FFT = 31.25 Hz bins, 32 ms window
+const FFT_SIZE: usize = 512;
+// ~30 frames per second is plenty for the overlay
+const PUBLISH_INTERVAL_SAMPLES: usize = 16000 / 30;
+pub const SPECTRUM_BANDS: usize = 16;
+const LOWEST_BAND_HZ: f32 = 62.5;
+// Everything below is reported as silence
+const FLOOR_DBFS: f32 = -100.0;
+
+/// One overlay update, emitted as `"audio_meter"` while the overlay is visible.
+/// Levels are dBFS; a full-scale sine reads 0 dBFS peak, -3 dBFS RMS.
+#[derive(Debug, Clone, Serialize)]
+pub struct MeterFrame {
+    /// Highest sample magnitude since the previous frame.
+    pub peak_dbfs: f32,
+    /// RMS since the previous frame.
+    pub rms_dbfs: f32,
+    /// Log-spaced bands from 62.5 Hz to 8 kHz, dBFS (sine peak = 0).
+    pub bands_dbfs: Vec<f32>,
+}
+
+fn to_dbfs(linear: f32) -> f32 {
+    if linear <= 0.0 {
+        return FLOOR_DBFS;
+    }
+    (20.0 * linear.log10()).max(FLOOR_DBFS)
+}
+
+/// Peak/RMS/spectrum meter, fed block by block from the pipeline.
+pub struct Meter {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    // Last FFT_SIZE samples, oldest first
+    history: Vec<f32>,
+    input: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    // Bin range [start, end) per band
+    bands: Vec<(usize, usize)>,
+    peak: f32,
+    sum_squares: f64,
+    count: usize,
+    // Samples owed to the publish clock, carried across frames so the rate
+    // doesn't depend on the device block size
+    pending: usize,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Meter {
+    pub fn new() -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let input = fft.make_input_vec();
+        let spectrum = fft.make_output_vec();
+
+        Self {
+            fft,
+            window,
+            history: vec![0.0; FFT_SIZE],
+            input,
+            spectrum,
+            bands: Self::band_bins(),
+            peak: 0.0,
+            sum_squares: 0.0,
+            count: 0,
+            pending: 0,
+        }
+    }
+
+    // Log-spaced band edges between LOWEST_BAND_HZ and Nyquist, at least one bin each
+    fn band_bins() -> Vec<(usize, usize)> {
+        let bin_hz = SAMPLE_RATE / FFT_SIZE as f32;
+        let nyquist = SAMPLE_RATE / 2.0;
+        let ratio = (nyquist / LOWEST_BAND_HZ).powf(1.0 / SPECTRUM_BANDS as f32);
+        let last_bin = FFT_SIZE / 2;
+
+        let mut bins = Vec::with_capacity(SPECTRUM_BANDS);
+        let mut start = (LOWEST_BAND_HZ / bin_hz).round() as usize;
+        for b in 0..SPECTRUM_BANDS {
+            let edge_hz = LOWEST_BAND_HZ * ratio.powi(b as i32 + 1);
+            let end = ((edge_hz / bin_hz).round() as usize)
+                .max(start + 1)
+                .min(last_bin + 1);
+            bins.push((start, end));
+            start = end.min(last_bin);
+        }
+        bins
+    }
+
+    /// Feeds 16 kHz mono samples. Returns a frame roughly every 33 ms of audio.
+    pub fn push(&mut self, samples: &[f32]) -> Option<MeterFrame> {
+        for &s in samples {
+            self.peak = self.peak.max(s.abs());
+            self.sum_squares += (s * s) as f64;
+        }
+        self.count += samples.len();
+        self.pending += samples.len();
+
+        // Keep the newest FFT_SIZE samples
+        if samples.len() >= FFT_SIZE {
+            self.history
+                .copy_from_slice(&samples[samples.len() - FFT_SIZE..]);
+        } else {
+            self.history.drain(..samples.len());
+            self.history.extend_from_slice(samples);
+        }
+
+        if self.pending < PUBLISH_INTERVAL_SAMPLES {
+            return None;
+        }
+        self.pending = (self.pending - PUBLISH_INTERVAL_SAMPLES) % PUBLISH_INTERVAL_SAMPLES;
+
+        let frame = MeterFrame {
+            peak_dbfs: to_dbfs(self.peak),
+            rms_dbfs: to_dbfs((self.sum_squares / self.count as f64).sqrt() as f32),
+            bands_dbfs: self.spectrum_bands(),
+        };
+        self.peak = 0.0;
+        self.sum_squares = 0.0;
+        self.count = 0;
+        Some(frame)
+    }
+
+    fn spectrum_bands(&mut self) -> Vec<f32> {
+        for ((x, h), w) in self.input.iter_mut().zip(&self.history).zip(&self.window) {
+            *x = h * w;
+        }
+        if self
+            .fft
+            .process(&mut self.input, &mut self.spectrum)
+            .is_err()
+        {
+            return vec![FLOOR_DBFS; SPECTRUM_BANDS];
+        }
+
+        // A full-scale sine peaks at N/4 with a Hann window, and the window
+        // spreads its energy over 1.5 bins
+        let reference = (FFT_SIZE / 4) as f32 * 1.5f32.sqrt();
+        self.bands
+            .iter()
+            .map(|&(start, end)| {
+                let energy: f32 = self.spectrum[start..end].iter().map(|c| c.norm_sqr()).sum();
+                to_dbfs(energy.sqrt() / reference)
+            })
+            .collect()
+    }
+}
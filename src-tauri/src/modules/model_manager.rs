@@ -0,0 +1,197 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use whisper_rs::{WhisperContext, WhisperContextParameters};
+
+// Keeps the selected Whisper model resident between dictations. Loading
+// large-v3-turbo takes seconds, so the context is loaded once, swapped in the
+// background when `selected_model` changes and dropped after an idle period.
+// Whisper states hold their own reference to the context, so a swap or unload
+// never pulls the model out from under a running transcription.
+
+/// Persisted as `"models"` in config.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelSettings {
+    /// Unload the model after this long without a transcription; 0 = keep it loaded.
+    pub idle_unload_secs: u64,
+    /// Load the selected model at startup instead of on the first dictation.
+    pub preload: bool,
+}
+
+impl Default for ModelSettings {
+    fn default() -> Self {
+        Self {
+            idle_unload_secs: 600,
+            preload: true,
+        }
+    }
+}
+
+/// Reported by `get_model_status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelStatus {
+    pub selected: String,
+    /// The model currently in memory, if any.
+    pub loaded: Option<String>,
+    pub idle_secs: Option<u64>,
+}
+
+struct LoadedModel {
+    filename: String,
+    ctx: Arc<WhisperContext>,
+    last_used: Instant,
+}
+
+pub struct ModelManager {
+    base_path: PathBuf,
+    selected: Arc<Mutex<String>>,
+    settings: Arc<Mutex<ModelSettings>>,
+    // Held while loading, so a dictation waits for a swap instead of loading twice
+    loaded: Mutex<Option<LoadedModel>>,
+    // Selected model that could not be loaded, so it is reported once and not retried every tick
+    failed: Mutex<Option<String>>,
+}
+
+impl ModelManager {
+    pub fn new(
+        base_path: PathBuf,
+        selected: Arc<Mutex<String>>,
+        settings: Arc<Mutex<ModelSettings>>,
+    ) -> Self {
+        Self {
+            base_path,
+            selected,
+            settings,
+            loaded: Mutex::new(None),
+            failed: Mutex::new(None),
+        }
+    }
+
+    pub fn selected(&self) -> String {
+        self.selected.lock().clone()
+    }
+
+    /// Context of the selected model, loading (or swapping to) it if needed.
+    /// Returns the model's file name with it.
+    pub fn acquire(&self) -> Result<(String, Arc<WhisperContext>), String> {
+        let filename = self.selected();
+        self.acquire_model(&filename).map(|ctx| (filename, ctx))
+    }
+
+    /// Context of `filename`. The selected model is kept resident; any other one
+    /// (e.g. re-transcribing an archived session) is loaded just for the caller.
+    pub fn acquire_model(&self, filename: &str) -> Result<Arc<WhisperContext>, String> {
+        let mut loaded = self.loaded.lock();
+        if let Some(model) = loaded.as_mut().filter(|m| m.filename == filename) {
+            model.last_used = Instant::now();
+            return Ok(model.ctx.clone());
+        }
+
+        let ctx = Arc::new(self.load(filename)?);
+        *self.failed.lock() = None;
+        if filename == self.selected() {
+            *loaded = Some(LoadedModel {
+                filename: filename.to_string(),
+                ctx: ctx.clone(),
+                last_used: Instant::now(),
+            });
+        }
+        Ok(ctx)
+    }
+
+    /// Called periodically: swaps a resident model for a newly selected one,
+    /// preloads at startup and unloads after the idle timeout.
+    pub fn maintain(&self, startup: bool) {
+        let settings = self.settings.lock().clone();
+        let selected = self.selected();
+        // Don't wait behind a dictation that is loading right now
+        let Some(mut loaded) = self.loaded.try_lock() else {
+            return;
+        };
+
+        match loaded.as_mut() {
+            Some(model) => {
+                if Arc::strong_count(&model.ctx) > 1 {
+                    // A transcription is using it right now
+                    model.last_used = Instant::now();
+                }
+                if model.filename != selected {
+                    self.swap(&mut loaded, &selected);
+                } else if settings.idle_unload_secs > 0
+                    && model.last_used.elapsed() >= Duration::from_secs(settings.idle_unload_secs)
+                {
+                    println!(
+                        "[DEBUG] Unloading Whisper model {} after {} s idle",
+                        model.filename, settings.idle_unload_secs
+                    );
+                    *loaded = None;
+                }
+            }
+            None if startup && settings.preload => self.swap(&mut loaded, &selected),
+            None => {}
+        }
+    }
+
+    pub fn unload(&self) {
+        if self.loaded.lock().take().is_some() {
+            println!("[DEBUG] Whisper model unloaded");
+        }
+    }
+
+    pub fn status(&self) -> ModelStatus {
+        let loaded = self.loaded.lock();
+        ModelStatus {
+            selected: self.selected(),
+            loaded: loaded.as_ref().map(|m| m.filename.clone()),
+            idle_secs: loaded.as_ref().map(|m| m.last_used.elapsed().as_secs()),
+        }
+    }
+
+    // Replaces the resident model with `filename`. A model that can't be loaded
+    // (e.g. still downloading) leaves the old one in place.
+    fn swap(&self, loaded: &mut Option<LoadedModel>, filename: &str) {
+        if self.failed.lock().as_deref() == Some(filename) {
+            return;
+        }
+        match self.load(filename) {
+            Ok(ctx) => {
+                *loaded = Some(LoadedModel {
+                    filename: filename.to_string(),
+                    ctx: Arc::new(ctx),
+                    last_used: Instant::now(),
+                });
+                *self.failed.lock() = None;
+            }
+            Err(e) => {
+                println!("[WARNING] {}. Keeping the current model.", e);
+                *self.failed.lock() = Some(filename.to_string());
+            }
+        }
+    }
+
+    fn load(&self, filename: &str) -> Result<WhisperContext, String> {
+        let model_path = self.base_path.join(filename);
+        if !model_path.exists() {
+            return Err(format!(
+                "AI model not found. Please download {} in settings.",
+                filename
+            ));
+        }
+
+        let started = Instant::now();
+        let ctx = WhisperContext::new_with_params(
+            &model_path.to_string_lossy(),
+            WhisperContextParameters::default(),
+        )
+        .map_err(|e| format!("Failed to load {}: {}", filename, e))?;
+        println!(
+            "[DEBUG] Whisper model {} loaded in {} ms",
+            filename,
+            started.elapsed().as_millis()
+        );
+        Ok(ctx)
+    }
+}
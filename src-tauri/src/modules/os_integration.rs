@@ -80,6 +80,11 @@ impl OSIntegration {
         }
     }
 
+    /// What `paste_text` pasted last in test mode (`VIBEFLOW_TEST_MODE`).
+    pub fn get_mock_paste() -> String {
+        MOCK_CLIPBOARD.lock().unwrap().clone()
+    }
+
     pub fn execute_command(command: crate::modules::llm::Command) -> Result<()> {
         #[cfg(target_os = "linux")]
         {
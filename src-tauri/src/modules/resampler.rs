@@ -0,0 +1,78 @@
+use anyhow::Result;
+use rubato::{FftFixedIn, Resampler};
+
+/// Band-limited streaming resampler for mono f32 audio (rubato FFT resampler).
+///
+/// Accepts arbitrary callback-sized chunks. Filter history and not-yet-consumed
+/// input are kept across calls, and the filter's group delay is trimmed from the
+/// start, so output sample `n` always lines up with input time `n / dst_rate`
+/// no matter how the input was split.
+pub struct StreamResampler {
+    // None = same rate, pass through untouched
+    inner: Option<FftFixedIn<f32>>,
+    pending: Vec<f32>,
+    chunk_size: usize,
+    delay_left: usize,
+}
+
+impl StreamResampler {
+    pub fn new(src_rate: u32, dst_rate: u32) -> Result<Self> {
+        if src_rate == dst_rate {
+            return Ok(Self {
+                inner: None,
+                pending: Vec::new(),
+                chunk_size: 0,
+                delay_left: 0,
+            });
+        }
+
+        // 10 ms input chunks keep latency low; rubato rounds up to the
+        // smallest FFT size that fits the rate ratio.
+        let chunk_size = (src_rate as usize / 100).max(1);
+        let inner = FftFixedIn::<f32>::new(src_rate as usize, dst_rate as usize, chunk_size, 1, 1)?;
+        let delay_left = inner.output_delay();
+
+        Ok(Self {
+            inner: Some(inner),
+            pending: Vec::with_capacity(chunk_size * 2),
+            chunk_size,
+            delay_left,
+        })
+    }
+
+    /// Drops all buffered audio, e.g. when the stage is switched off and on again.
+    pub fn reset(&mut self) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.reset();
+            self.delay_left = inner.output_delay();
+        }
+        self.pending.clear();
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let Some(inner) = self.inner.as_mut() else {
+            return input.to_vec();
+        };
+
+        self.pending.extend_from_slice(input);
+
+        let mut output = Vec::new();
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= self.chunk_size {
+            let chunk: [&[f32]; 1] = [&self.pending[consumed..consumed + self.chunk_size]];
+            consumed += self.chunk_size;
+
+            match inner.process(&chunk[..], None) {
+                Ok(mut channels) => {
+                    let resampled = channels.swap_remove(0);
+                    let skip = self.delay_left.min(resampled.len());
+                    self.delay_left -= skip;
+                    output.extend_from_slice(&resampled[skip..]);
+                }
+                Err(e) => println!("[ERROR] Resampler failed: {}", e),
+            }
+        }
+        self.pending.drain(..consumed);
+        output
+    }
+}
@@ -1,17 +1,57 @@
 use std::sync::Arc;
 use parking_lot::Mutex;
-use tokio::sync::mpsc;
-use crate::modules::{inference::InferenceEngine, audio::SensitiveAudio};
+use crate::modules::{inference::InferenceEngine, audio_source::DeviceStatus, handoff::AudioSender, hotkeys::{HotkeyBehavior, HotkeyController, RecordingSessions}, archive::SessionArchive, calibration::{CalibrationProfiles, CalibrationRun}, channels::ChannelSettings, decoding::DecodingSettings, denoise::DenoiseSettings, dictionary::Dictionary, levels::{GainSettings, LevelReport}, loopback::MixSettings, language::LanguageSettings, model_manager::ModelSettings, transcript::TranscriptSettings, vad::VadSettings, wakeword::WakeWordSettings};
 use tauri_plugin_global_shortcut::{Modifiers, Code};
 
 #[allow(dead_code)]
 pub struct AppState {
     pub is_recording: Arc<Mutex<bool>>,
-    pub tx_audio: Arc<Mutex<Option<mpsc::Sender<SensitiveAudio>>>>,
+    // Producer side of the audio hand-off; also the source of its back-pressure counters
+    pub tx_audio: Arc<Mutex<Option<AudioSender>>>,
     pub inference_engine: Arc<InferenceEngine>,
     pub amplitude: Arc<Mutex<f32>>, 
     pub selected_device: Arc<Mutex<Option<String>>>,
+    // What the supervised stream is actually running on (may be a fallback)
+    pub active_device: Arc<Mutex<DeviceStatus>>,
     pub hotkey_modifiers: Arc<Mutex<Modifiers>>,
     pub hotkey_code: Arc<Mutex<Code>>,
+    // Toggle or push-to-talk for the main hotkey
+    pub hotkey_behavior: Arc<Mutex<HotkeyBehavior>>,
+    pub hotkey_controller: Arc<Mutex<HotkeyController>>,
+    // Numbered by start_recording; a push-to-talk tap that was too short is discarded
+    pub recording_sessions: Arc<Mutex<RecordingSessions>>,
     pub selected_model: Arc<Mutex<String>>,
+    pub model_settings: Arc<Mutex<ModelSettings>>,
+    // Default language, "auto" detection and per-app rules
+    pub language_settings: Arc<Mutex<LanguageSettings>>,
+    // User vocabulary, saved to dictionary.json
+    pub dictionary: Arc<Mutex<Dictionary>>,
+    // Whisper decoding for ghost text and for the final passes
+    pub decoding_settings: Arc<Mutex<DecodingSettings>>,
+    // Whether doubtful transcripts are dropped instead of pasted
+    pub transcript_settings: Arc<Mutex<TranscriptSettings>>,
+    pub vad_settings: Arc<Mutex<VadSettings>>,
+    // Set by the audio callback when VAD auto-stop fires; consumed by the main loop
+    pub auto_stop_requested: Arc<Mutex<bool>>,
+    pub denoise_settings: Arc<Mutex<DenoiseSettings>>,
+    pub archive: Arc<SessionArchive>,
+    pub capture_secs: Arc<Mutex<u32>>,
+    pub capture_request: Arc<Mutex<Option<u32>>>,
+    pub capture_hotkey: Arc<Mutex<Option<(Modifiers, Code)>>>,
+    // Flips the translate toggle
+    pub translate_hotkey: Arc<Mutex<Option<(Modifiers, Code)>>>,
+    pub gain_settings: Arc<Mutex<GainSettings>>,
+    pub level_report: Arc<Mutex<LevelReport>>,
+    pub channel_settings: Arc<Mutex<ChannelSettings>>,
+    // Set while the overlay is shown; gates the audio meter
+    pub overlay_visible: Arc<Mutex<bool>>,
+    // Visualizers listening to the meter (watch_audio_meter); also keeps it running
+    pub meter_watchers: Arc<Mutex<usize>>,
+    pub mix_settings: Arc<Mutex<MixSettings>>,
+    pub wake_word: Arc<Mutex<WakeWordSettings>>,
+    pub calibration: Arc<Mutex<CalibrationProfiles>>,
+    // Measurements of the calibration wizard in progress
+    pub calibration_run: Arc<Mutex<CalibrationRun>>,
+    // Handed to the running pipeline while a calibration step records
+    pub calibration_tap: Arc<Mutex<Option<AudioSender>>>,
 }
@@ -0,0 +1,214 @@
+use serde::Serialize;
+use zeroize::Zeroize;
+
+// Ghost text without re-decoding the whole recording on every pass: each pass
+// decodes only the window of audio after the last committed word. A word is
+// committed once two consecutive passes agree on it (LocalAgreement-2); the
+// window then moves past it and committed words are never decoded again.
+
+const SAMPLE_RATE: usize = 16000;
+// Whisper tends to repeat the last committed words at the start of a new window
+const MAX_OVERLAP_WORDS: usize = 5;
+const OVERLAP_SAMPLES: usize = SAMPLE_RATE;
+// Committed text handed to the next pass as context
+const PROMPT_CHARS: usize = 200;
+
+/// A decoded word. Times are absolute sample positions in the session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    /// Including Whisper's leading space, so words join by concatenation.
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+    /// Lowest probability among its tokens.
+    pub probability: f32,
+}
+
+// Security: words are transcript text
+impl Drop for Word {
+    fn drop(&mut self) {
+        self.text.zeroize();
+    }
+}
+
+/// Payload of `transcript_partial`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PartialTranscript {
+    /// Final; only ever grows during a session.
+    pub committed: String,
+    /// Latest guess for the rest; may still change.
+    pub tentative: String,
+}
+
+/// Groups Whisper tokens into words: a token starting with a space begins a new
+/// word. Works on bytes, since one character can be split across tokens.
+#[derive(Debug, Default)]
+pub struct WordBuilder {
+    words: Vec<Word>,
+    bytes: Vec<u8>,
+    start: usize,
+    end: usize,
+    probability: f32,
+}
+
+impl WordBuilder {
+    /// `probability` is Whisper's probability for the token.
+    pub fn push_token(&mut self, bytes: &[u8], start: usize, end: usize, probability: f32) {
+        if bytes.is_empty() {
+            return;
+        }
+        if bytes[0] == b' ' && !self.bytes.is_empty() {
+            self.flush();
+        }
+        if self.bytes.is_empty() {
+            self.start = start;
+            self.probability = probability;
+        }
+        self.probability = self.probability.min(probability);
+        self.bytes.extend_from_slice(bytes);
+        self.end = end.max(self.start);
+    }
+
+    pub fn finish(mut self) -> Vec<Word> {
+        self.flush();
+        self.words
+    }
+
+    fn flush(&mut self) {
+        let text = String::from_utf8_lossy(&self.bytes).into_owned();
+        self.bytes.zeroize();
+        if !text.trim().is_empty() {
+            self.words.push(Word {
+                text,
+                start: self.start,
+                end: self.end,
+                probability: self.probability,
+            });
+        }
+    }
+}
+
+/// Commits the words two consecutive hypotheses agree on.
+#[derive(Debug, Default)]
+pub struct LocalAgreement {
+    committed: Vec<Word>,
+    // Uncommitted rest of the previous hypothesis
+    previous: Vec<Word>,
+    window_start: usize,
+}
+
+impl LocalAgreement {
+    /// Session sample the next pass should start decoding at.
+    pub fn window_start(&self) -> usize {
+        self.window_start
+    }
+
+    /// Feeds a hypothesis for the audio from `window_start` on.
+    pub fn insert(&mut self, hypothesis: Vec<Word>) -> PartialTranscript {
+        let hypothesis = self.drop_overlap(hypothesis);
+        let agreed = hypothesis
+            .iter()
+            .zip(&self.previous)
+            .take_while(|(a, b)| normalize(&a.text) == normalize(&b.text))
+            .count();
+
+        let mut hypothesis = hypothesis;
+        let rest = hypothesis.split_off(agreed);
+        self.commit(hypothesis);
+        self.previous = rest;
+        self.partial()
+    }
+
+    /// Ends the window at `cut` (see `longform`): commits the words of `words`,
+    /// a final pass over the window plus some overlap, that start before the
+    /// cut. Later words are decoded again with the next window.
+    pub fn cut(&mut self, words: Vec<Word>, cut: usize) -> PartialTranscript {
+        let mut words = self.drop_overlap(words);
+        let before = words.iter().take_while(|w| w.start < cut).count();
+        words.truncate(before);
+        self.previous.clear();
+        self.commit(words);
+        self.window_start = self.window_start.max(cut);
+        self.partial()
+    }
+
+    pub fn partial(&self) -> PartialTranscript {
+        let committed = join(&self.committed).trim_start().to_string();
+        let mut tentative = join(&self.previous);
+        if committed.is_empty() {
+            tentative = tentative.trim_start().to_string();
+        }
+        PartialTranscript {
+            committed,
+            tentative,
+        }
+    }
+
+    /// Tail of the committed text, as context for decoding the next window.
+    pub fn prompt(&self) -> String {
+        let text = join(&self.committed);
+        let skip = text.chars().count().saturating_sub(PROMPT_CHARS);
+        text.chars()
+            .skip(skip)
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
+    /// All words of the session: the committed ones plus `last`, the final pass
+    /// over the remaining window (`None` if no audio was left, keeping the last
+    /// guess).
+    pub fn finish(mut self, last: Option<Vec<Word>>) -> Vec<Word> {
+        let last = match last {
+            Some(words) => self.drop_overlap(words),
+            None => std::mem::take(&mut self.previous),
+        };
+        self.commit(last);
+        std::mem::take(&mut self.committed)
+    }
+
+    fn commit(&mut self, words: Vec<Word>) {
+        if let Some(last) = words.last() {
+            self.window_start = self.window_start.max(last.end);
+        }
+        self.committed.extend(words);
+    }
+
+    // Leading words that repeat the committed tail right at the window start
+    fn drop_overlap(&self, mut hypothesis: Vec<Word>) -> Vec<Word> {
+        let Some(first) = hypothesis.first() else {
+            return hypothesis;
+        };
+        if first.start >= self.window_start + OVERLAP_SAMPLES {
+            return hypothesis;
+        }
+        let max = MAX_OVERLAP_WORDS
+            .min(self.committed.len())
+            .min(hypothesis.len());
+        for n in (1..=max).rev() {
+            let tail = &self.committed[self.committed.len() - n..];
+            let head = &hypothesis[..n];
+            if tail
+                .iter()
+                .zip(head)
+                .all(|(a, b)| normalize(&a.text) == normalize(&b.text))
+            {
+                hypothesis.drain(..n);
+                break;
+            }
+        }
+        hypothesis
+    }
+}
+
+// Compared without case and punctuation, which Whisper changes between passes
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn join(words: &[Word]) -> String {
+    words.iter().map(|w| w.text.as_str()).collect()
+}
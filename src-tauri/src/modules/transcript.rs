@@ -0,0 +1,289 @@
+use crate::modules::streaming::Word;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+// What Whisper heard, with enough detail to judge it: segments, word timings,
+// token probabilities, the language and how much speech the VAD heard after the
+// hotkey. Lets the UI mark doubtful words and, if enabled, keeps garbage
+// (hallucinations on silence, repetition loops) from being pasted.
+
+const SAMPLES_PER_MS: usize = 16;
+/// Words below this probability are worth a second look.
+pub const LOW_CONFIDENCE: f32 = 0.5;
+// Less speech than this (per the VAD) is treated as silence or noise
+const LITTLE_SPEECH_MS: u64 = 200;
+// Like Whisper's no-speech rule: little speech and not confident about the text
+const NO_SPEECH_MAX_CONFIDENCE: f32 = 0.5;
+// Text this unsure is noise, whatever the VAD says
+const GARBAGE_CONFIDENCE: f32 = 0.25;
+// Decoding loops: the same 1-3 words over and over
+const MAX_LOOP_NGRAM: usize = 3;
+const MIN_LOOP_REPEATS: usize = 5;
+
+// What Whisper makes up on silence and noise. Never dictated on their own
+const HALLUCINATIONS: &[&str] = &[
+    "thanks for watching",
+    "thank you for watching",
+    "thank you so much for watching",
+    "please subscribe",
+    "like and subscribe",
+    "subtitles by the amaraorg community",
+];
+// ...and ones that could be real, so only rejected when the VAD heard little
+const LIKELY_HALLUCINATIONS: &[&str] = &["thank you", "thanks", "bye", "you", "okay"];
+
+/// Whether doubtful transcripts are dropped instead of pasted (`"transcript"` in config.json).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptSettings {
+    /// Off by default: a short real dictation ("Okay.", "Thanks") can look like garbage.
+    pub reject_garbage: bool,
+}
+
+/// A transcribed word.
+#[derive(Debug, Clone, PartialEq, Serialize, Zeroize, ZeroizeOnDrop)]
+pub struct TranscriptWord {
+    /// Without surrounding whitespace.
+    pub text: String,
+    /// From the start of the session.
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Lowest probability among its tokens, so one doubtful piece marks the word.
+    pub probability: f32,
+}
+
+/// A run of words, as Whisper segmented it (or a sentence of streamed text).
+#[derive(Debug, Clone, PartialEq, Serialize, Zeroize, ZeroizeOnDrop)]
+pub struct TranscriptSegment {
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub words: Vec<TranscriptWord>,
+}
+
+/// Why a transcript looks like garbage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rejection {
+    /// Likely silence or noise, and Whisper isn't sure of the text.
+    NoSpeech,
+    /// Text Whisper produces on silence, e.g. "Thanks for watching!" or "[BLANK_AUDIO]".
+    Hallucination,
+    /// Whisper got stuck repeating itself.
+    Repetition,
+    /// Almost every word is doubtful.
+    LowConfidence,
+}
+
+// Security: Protected Transcript
+#[derive(Zeroize, ZeroizeOnDrop, Debug)]
+pub struct SensitiveTranscript {
+    text: String,
+    segments: Vec<TranscriptSegment>,
+    // Language Whisper transcribed in (detected in "auto" mode)
+    language: Option<String>,
+    // Whisper translated the speech to English
+    translated: bool,
+    // Speech the VAD heard after the hotkey (the pre-roll doesn't count);
+    // `None` when unknown
+    speech_ms: Option<u64>,
+}
+
+impl SensitiveTranscript {
+    /// Plain text without details, e.g. an error message.
+    pub fn new(s: String) -> Self {
+        Self {
+            text: s,
+            segments: Vec::new(),
+            language: None,
+            translated: false,
+            speech_ms: None,
+        }
+    }
+
+    /// From Whisper's segments.
+    pub fn from_segments(
+        segments: Vec<Vec<Word>>,
+        language: Option<String>,
+        translated: bool,
+        speech_ms: Option<u64>,
+    ) -> Self {
+        let mut text = join(segments.iter().flatten());
+        let trimmed = text.trim().to_string();
+        text.zeroize();
+        Self {
+            text: trimmed,
+            segments: segments.iter().filter_map(|words| segment(words)).collect(),
+            language,
+            translated,
+            speech_ms,
+        }
+    }
+
+    /// From streamed words, split into sentences.
+    pub fn from_words(
+        words: Vec<Word>,
+        language: Option<String>,
+        translated: bool,
+        speech_ms: Option<u64>,
+    ) -> Self {
+        let mut sentences = vec![Vec::new()];
+        for word in words {
+            let ends_sentence = word
+                .text
+                .trim_end()
+                .ends_with(['.', '?', '!', '。', '？', '！']);
+            sentences.last_mut().unwrap().push(word);
+            if ends_sentence {
+                sentences.push(Vec::new());
+            }
+        }
+        Self::from_segments(sentences, language, translated, speech_ms)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+    pub fn segments(&self) -> &[TranscriptSegment] {
+        &self.segments
+    }
+    pub fn words(&self) -> impl Iterator<Item = &TranscriptWord> {
+        self.segments.iter().flat_map(|s| s.words.iter())
+    }
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+    pub fn is_translated(&self) -> bool {
+        self.translated
+    }
+    /// Speech the VAD heard after the hotkey, if known.
+    pub fn speech_ms(&self) -> Option<u64> {
+        self.speech_ms
+    }
+
+    /// Average word probability; `None` without word details.
+    pub fn confidence(&self) -> Option<f32> {
+        let (sum, count) = self
+            .words()
+            .fold((0.0, 0), |(sum, count), w| (sum + w.probability, count + 1));
+        (count > 0).then(|| sum / count as f32)
+    }
+
+    pub fn low_confidence_words(&self) -> impl Iterator<Item = &TranscriptWord> {
+        self.words().filter(|w| w.probability < LOW_CONFIDENCE)
+    }
+
+    /// Whether this looks like garbage rather than dictation. Only acted on with
+    /// `reject_garbage`: it can't tell a real "Thanks." over little speech from a made-up one.
+    pub fn rejection(&self) -> Option<Rejection> {
+        let little_speech = self.speech_ms.is_some_and(|ms| ms < LITTLE_SPEECH_MS);
+        let normalized = normalize(&self.text);
+        if normalized.is_empty() {
+            // Nothing but annotations like "[BLANK_AUDIO]" or "(music)"
+            return self
+                .text
+                .chars()
+                .any(char::is_alphanumeric)
+                .then_some(Rejection::Hallucination);
+        }
+        if HALLUCINATIONS.contains(&normalized.as_str())
+            || (little_speech && LIKELY_HALLUCINATIONS.contains(&normalized.as_str()))
+        {
+            return Some(Rejection::Hallucination);
+        }
+        if is_loop(&normalized) {
+            return Some(Rejection::Repetition);
+        }
+        let confidence = self.confidence()?;
+        if little_speech && confidence < NO_SPEECH_MAX_CONFIDENCE {
+            return Some(Rejection::NoSpeech);
+        }
+        (confidence < GARBAGE_CONFIDENCE).then_some(Rejection::LowConfidence)
+    }
+}
+
+/// Milliseconds of `speech_samples` (16 kHz), e.g. from the session's VAD.
+pub fn speech_ms(speech_samples: usize) -> u64 {
+    to_ms(speech_samples)
+}
+
+fn segment(words: &[Word]) -> Option<TranscriptSegment> {
+    let (first, last) = (words.first()?, words.last()?);
+    let mut text = join(words);
+    let segment = TranscriptSegment {
+        text: text.trim().to_string(),
+        start_ms: to_ms(first.start),
+        end_ms: to_ms(last.end),
+        words: words
+            .iter()
+            .map(|w| TranscriptWord {
+                text: w.text.trim().to_string(),
+                start_ms: to_ms(w.start),
+                end_ms: to_ms(w.end),
+                probability: w.probability,
+            })
+            .collect(),
+    };
+    text.zeroize();
+    Some(segment)
+}
+
+fn join<'a>(words: impl IntoIterator<Item = &'a Word>) -> String {
+    words.into_iter().map(|w| w.text.as_str()).collect()
+}
+
+fn to_ms(sample: usize) -> u64 {
+    (sample / SAMPLES_PER_MS) as u64
+}
+
+// Lowercase words without punctuation or annotations
+fn normalize(text: &str) -> String {
+    strip_annotations(text)
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Drops "[...]", "(...)" and "*...*" spans, which Whisper uses for non-speech
+fn strip_annotations(text: &str) -> String {
+    let mut out = String::new();
+    let mut closing = None;
+    for c in text.chars() {
+        match closing {
+            Some(close) if c == close => closing = None,
+            Some(_) => {}
+            None => match c {
+                '[' => closing = Some(']'),
+                '(' => closing = Some(')'),
+                '*' => closing = Some('*'),
+                _ => out.push(c),
+            },
+        }
+    }
+    out
+}
+
+// Some 1-3 word phrase repeated back to back `MIN_LOOP_REPEATS` times
+fn is_loop(normalized: &str) -> bool {
+    let words: Vec<&str> = normalized.split_whitespace().collect();
+    if words.len() < MIN_LOOP_REPEATS {
+        return false;
+    }
+    (1..=MAX_LOOP_NGRAM).any(|n| {
+        (0..n).any(|phase| {
+            let chunks: Vec<&[&str]> = words[phase..].chunks_exact(n).collect();
+            let mut run = 1;
+            for pair in chunks.windows(2) {
+                run = if pair[0] == pair[1] { run + 1 } else { 1 };
+                if run >= MIN_LOOP_REPEATS {
+                    return true;
+                }
+            }
+            false
+        })
+    })
+}
@@ -0,0 +1,361 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use tract_onnx::prelude::*;
+use webrtc_vad::{SampleRate, Vad, VadMode};
+
+// All detectors run on the resampled 16 kHz mono stream
+const VAD_SAMPLE_RATE: usize = 16000;
+// WebRTC VAD only accepts 10/20/30 ms frames. 30 ms at 16 kHz = 480 samples.
+const WEBRTC_FRAME_SIZE: usize = 480;
+// Silero v5 takes 512-sample windows at 16 kHz plus 64 samples of context
+const SILERO_FRAME_SIZE: usize = 512;
+const SILERO_CONTEXT_SIZE: usize = 64;
+const SILERO_STATE_SHAPE: [usize; 3] = [2, 1, 128];
+pub const SILERO_MODEL_FILENAME: &str = "silero_vad.onnx";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VadBackend {
+    WebRtc,
+    Silero,
+}
+
+/// Hands-free settings, persisted as `"vad"` in config.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadSettings {
+    /// End a hotkey-started recording after `silence_timeout_ms` of trailing silence.
+    pub auto_stop: bool,
+    pub backend: VadBackend,
+    /// WebRTC only: 0 (least aggressive) ..= 3 (most aggressive), maps to `VadMode`.
+    pub aggressiveness: u8,
+    /// Silero only: speech probability above which a frame counts as speech.
+    pub silero_threshold: f32,
+    /// How long a frame keeps counting as speech after the VAD last fired.
+    pub hangover_ms: u32,
+    /// Trailing silence (after speech was heard) that ends the recording.
+    pub silence_timeout_ms: u32,
+}
+
+impl Default for VadSettings {
+    fn default() -> Self {
+        Self {
+            auto_stop: false,
+            backend: VadBackend::WebRtc,
+            aggressiveness: 2,
+            silero_threshold: 0.5,
+            hangover_ms: 300,
+            silence_timeout_ms: 1500,
+        }
+    }
+}
+
+/// A frame-based speech/non-speech classifier for 16 kHz mono audio.
+pub trait VoiceActivityDetector: Send {
+    /// Number of samples `is_speech` expects per call.
+    fn frame_size(&self) -> usize;
+    fn is_speech(&mut self, frame: &[f32]) -> bool;
+    /// Clears any recurrent state between sessions.
+    fn reset(&mut self);
+}
+
+// --- WebRTC (GMM based, cheap, but triggers on keyboard clatter) ---
+
+// Threading: libfvad keeps its state behind a raw pointer, so `Vad` is not `Send`.
+// The instance is created for one stream and only ever touched from that stream's callback.
+struct SendVad(Vad);
+unsafe impl Send for SendVad {}
+
+pub struct WebRtcVad {
+    vad: SendVad,
+    mode: u8,
+    frame: Vec<i16>,
+}
+
+impl WebRtcVad {
+    pub fn new(aggressiveness: u8) -> Self {
+        Self {
+            vad: SendVad(Vad::new_with_rate_and_mode(
+                SampleRate::Rate16kHz,
+                Self::vad_mode(aggressiveness),
+            )),
+            mode: aggressiveness,
+            frame: Vec::with_capacity(WEBRTC_FRAME_SIZE),
+        }
+    }
+
+    fn vad_mode(aggressiveness: u8) -> VadMode {
+        match aggressiveness {
+            0 => VadMode::Quality,
+            1 => VadMode::LowBitrate,
+            2 => VadMode::Aggressive,
+            _ => VadMode::VeryAggressive,
+        }
+    }
+}
+
+impl VoiceActivityDetector for WebRtcVad {
+    fn frame_size(&self) -> usize {
+        WEBRTC_FRAME_SIZE
+    }
+
+    fn is_speech(&mut self, frame: &[f32]) -> bool {
+        self.frame.clear();
+        self.frame.extend(
+            frame
+                .iter()
+                .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+        );
+        self.vad.0.is_voice_segment(&self.frame).unwrap_or(false)
+    }
+
+    fn reset(&mut self) {
+        // fvad_reset also resets the mode and rate
+        self.vad.0.reset();
+        self.vad.0.set_sample_rate(SampleRate::Rate16kHz);
+        self.vad.0.set_mode(Self::vad_mode(self.mode));
+    }
+}
+
+// --- Silero (neural, ONNX via tract) ---
+
+type SileroModel = TypedRunnableModel<TypedModel>;
+
+pub struct SileroVad {
+    model: SileroModel,
+    state: Tensor,
+    context: Vec<f32>,
+    input: Vec<f32>,
+    threshold: f32,
+}
+
+impl SileroVad {
+    /// Loads the Silero v5 ONNX model (input, state, sr -> output, stateN).
+    pub fn load(model_path: &Path, threshold: f32) -> Result<Self> {
+        if !model_path.exists() {
+            return Err(anyhow!("Silero VAD model not found at {:?}", model_path));
+        }
+
+        let model = tract_onnx::onnx()
+            .model_for_path(model_path)?
+            .with_input_fact(0, f32::fact([1, SILERO_CONTEXT_SIZE + SILERO_FRAME_SIZE]).into())?
+            .with_input_fact(1, f32::fact(SILERO_STATE_SHAPE).into())?
+            .with_input_fact(2, i64::scalar_fact().into())?
+            .into_optimized()?
+            .into_runnable()?;
+
+        Ok(Self {
+            model,
+            state: Tensor::zero::<f32>(&SILERO_STATE_SHAPE)?,
+            context: vec![0.0; SILERO_CONTEXT_SIZE],
+            input: Vec::with_capacity(SILERO_CONTEXT_SIZE + SILERO_FRAME_SIZE),
+            threshold,
+        })
+    }
+
+    fn speech_probability(&mut self, frame: &[f32]) -> Result<f32> {
+        self.input.clear();
+        self.input.extend_from_slice(&self.context);
+        self.input.extend_from_slice(frame);
+
+        let input = Tensor::from_shape(&[1, self.input.len()], &self.input)?;
+        let sr = tensor0(VAD_SAMPLE_RATE as i64);
+        let outputs = self
+            .model
+            .run(tvec!(input.into(), self.state.clone().into(), sr.into()))?;
+
+        self.state = outputs[1].clone().into_tensor();
+        self.context
+            .copy_from_slice(&frame[frame.len() - SILERO_CONTEXT_SIZE..]);
+
+        outputs[0]
+            .as_slice::<f32>()?
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow!("Silero VAD returned no output"))
+    }
+}
+
+impl VoiceActivityDetector for SileroVad {
+    fn frame_size(&self) -> usize {
+        SILERO_FRAME_SIZE
+    }
+
+    fn is_speech(&mut self, frame: &[f32]) -> bool {
+        match self.speech_probability(frame) {
+            Ok(p) => p >= self.threshold,
+            Err(e) => {
+                println!("[ERROR] Silero VAD inference failed: {}", e);
+                false
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Ok(state) = Tensor::zero::<f32>(&SILERO_STATE_SHAPE) {
+            self.state = state;
+        }
+        self.context.iter_mut().for_each(|s| *s = 0.0);
+    }
+}
+
+/// Builds the detector selected in `settings`. Falls back to WebRTC when the
+/// Silero model is missing or fails to load, so auto-stop keeps working.
+pub fn create_detector(settings: &VadSettings, model_dir: &Path) -> Box<dyn VoiceActivityDetector> {
+    match settings.backend {
+        VadBackend::WebRtc => Box::new(WebRtcVad::new(settings.aggressiveness)),
+        VadBackend::Silero => {
+            let path = model_dir.join(SILERO_MODEL_FILENAME);
+            match SileroVad::load(&path, settings.silero_threshold) {
+                Ok(vad) => {
+                    println!("[DEBUG] Silero VAD loaded from {:?}", path);
+                    Box::new(vad)
+                }
+                Err(e) => {
+                    println!("[WARNING] {}. Falling back to WebRTC VAD.", e);
+                    Box::new(WebRtcVad::new(settings.aggressiveness))
+                }
+            }
+        }
+    }
+}
+
+/// Whether `a` and `b` would build the same detector (the timing settings
+/// don't affect it).
+fn same_detector(a: &VadSettings, b: &VadSettings) -> bool {
+    a.backend == b.backend
+        && match a.backend {
+            VadBackend::WebRtc => a.aggressiveness == b.aggressiveness,
+            VadBackend::Silero => a.silero_threshold == b.silero_threshold,
+        }
+}
+
+/// Classifies the 16 kHz stream frame by frame and reports when a recording
+/// has been followed by enough trailing silence to stop it.
+pub struct AutoStopDetector {
+    vad: Box<dyn VoiceActivityDetector>,
+    // Detectors for changed settings are built on a loader thread, since loading
+    // and optimizing the Silero model is far too slow for the audio callback.
+    // Until one arrives the previous detector keeps running.
+    load_requests: Sender<VadSettings>,
+    loaded: Receiver<Box<dyn VoiceActivityDetector>>,
+    // Settings the newest requested detector is built from
+    requested: VadSettings,
+    frame: Vec<f32>,
+    // All durations are tracked in samples since frame sizes differ per backend
+    hangover_samples: usize,
+    timeout_samples: usize,
+    hangover_left: usize,
+    silent_samples: usize,
+    speech_seen: bool,
+}
+
+impl AutoStopDetector {
+    /// Builds the first detector right away: call this when the stream is set
+    /// up, not from its callback.
+    pub fn new(settings: &VadSettings, model_dir: PathBuf) -> Self {
+        let (load_requests, requests) = mpsc::channel::<VadSettings>();
+        let (done, loaded) = mpsc::channel();
+        let vad = create_detector(settings, &model_dir);
+        thread::spawn(move || {
+            // Ends when the detector (and with it the sender) is dropped
+            while let Ok(mut settings) = requests.recv() {
+                // Only the newest of several quick changes is worth loading
+                while let Ok(newer) = requests.try_recv() {
+                    settings = newer;
+                }
+                if done.send(create_detector(&settings, &model_dir)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut detector = Self {
+            vad,
+            load_requests,
+            loaded,
+            requested: settings.clone(),
+            frame: Vec::new(),
+            hangover_samples: 0,
+            timeout_samples: 0,
+            hangover_left: 0,
+            silent_samples: 0,
+            speech_seen: false,
+        };
+        detector.configure(settings);
+        detector
+    }
+
+    /// Applies (possibly changed) settings and clears the per-session state.
+    /// Called whenever a new recording starts. A different backend is loaded in
+    /// the background and swapped in by `push` once it is ready.
+    pub fn configure(&mut self, settings: &VadSettings) {
+        if !same_detector(settings, &self.requested) {
+            let _ = self.load_requests.send(settings.clone());
+        }
+        self.requested = settings.clone();
+        self.swap_loaded();
+
+        self.vad.reset();
+        self.hangover_samples = ms_to_samples(settings.hangover_ms);
+        self.timeout_samples = ms_to_samples(settings.silence_timeout_ms).max(1);
+        self.frame.clear();
+        self.hangover_left = 0;
+        self.silent_samples = 0;
+        self.speech_seen = false;
+    }
+
+    // Switches to the newest detector the loader has finished, if any
+    fn swap_loaded(&mut self) {
+        let mut swapped = false;
+        while let Ok(vad) = self.loaded.try_recv() {
+            self.vad = vad;
+            swapped = true;
+        }
+        if swapped {
+            // The frame size may have changed; the silence count carries over
+            self.frame.clear();
+        }
+    }
+
+    /// Feeds 16 kHz mono samples. Returns `true` once speech has been heard
+    /// and has since been followed by at least the configured silence timeout.
+    pub fn push(&mut self, samples: &[f32]) -> bool {
+        self.swap_loaded();
+        let frame_size = self.vad.frame_size();
+        let mut timed_out = false;
+
+        for &sample in samples {
+            self.frame.push(sample);
+            if self.frame.len() < frame_size {
+                continue;
+            }
+
+            let voiced = self.vad.is_speech(&self.frame);
+            self.frame.clear();
+
+            if voiced {
+                self.speech_seen = true;
+                self.hangover_left = self.hangover_samples;
+                self.silent_samples = 0;
+            } else if self.hangover_left > 0 {
+                // Hangover: short gaps between words still count as speech
+                self.hangover_left = self.hangover_left.saturating_sub(frame_size);
+            } else if self.speech_seen {
+                self.silent_samples += frame_size;
+                if self.silent_samples >= self.timeout_samples {
+                    timed_out = true;
+                }
+            }
+        }
+
+        timed_out
+    }
+}
+
+fn ms_to_samples(ms: u32) -> usize {
+    ms as usize * VAD_SAMPLE_RATE / 1000
+}
@@ -0,0 +1,352 @@
+use crate::modules::audio::SensitiveAudio;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use tract_onnx::prelude::*;
+
+// Hands-free start: a small keyword model listens on the always-on 16 kHz stream
+// (between recordings only) and starts a session when its phrase is heard.
+//
+// Model contract: one ONNX file per phrase, `wakeword_<phrase>.onnx` in the app
+// data dir (e.g. `wakeword_hey_vibeflow.onnx`), taking `[1, 16000]` f32 samples
+// (the last second of audio) and returning the phrase probability as its first
+// output value.
+
+const SAMPLE_RATE: usize = 16000;
+pub const WAKE_WINDOW_SAMPLES: usize = SAMPLE_RATE;
+// Score the window every 100 ms
+const HOP_SAMPLES: usize = SAMPLE_RATE / 10;
+// Once above the threshold, wait this long for the score to peak
+const MAX_PEAK_WAIT_SAMPLES: usize = SAMPLE_RATE / 2;
+// Blocks waiting for the spotter thread (~1 s of 10 ms blocks); more are dropped
+const JOB_QUEUE_BLOCKS: usize = 100;
+
+/// Wake-word activation, persisted as `"wake_word"` in config.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WakeWordSettings {
+    pub enabled: bool,
+    /// Selects the model file, see `model_path`.
+    pub phrase: String,
+    /// Model probability above which the phrase counts as heard.
+    pub threshold: f32,
+    /// Ignore the phrase for this long after a recording ends.
+    pub cooldown_ms: u32,
+}
+
+impl Default for WakeWordSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            phrase: "hey vibeflow".to_string(),
+            threshold: 0.5,
+            cooldown_ms: 2000,
+        }
+    }
+}
+
+/// Model file for `phrase`: lowercase, anything but letters and digits becomes `_`.
+pub fn model_path(model_dir: &Path, phrase: &str) -> PathBuf {
+    let slug: String = phrase
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    model_dir.join(format!("wakeword_{}.onnx", slug))
+}
+
+/// Scores a window of 16 kHz mono audio for one phrase.
+pub trait KeywordSpotter: Send {
+    /// Probability (0..=1) that the window ends with the phrase.
+    fn score(&mut self, window: &[f32]) -> Result<f32>;
+}
+
+type KeywordModel = TypedRunnableModel<TypedModel>;
+
+pub struct OnnxKeywordSpotter {
+    model: KeywordModel,
+}
+
+impl OnnxKeywordSpotter {
+    pub fn load(model_path: &Path) -> Result<Self> {
+        if !model_path.exists() {
+            return Err(anyhow!("Wake-word model not found at {:?}", model_path));
+        }
+        let model = tract_onnx::onnx()
+            .model_for_path(model_path)?
+            .with_input_fact(0, f32::fact([1, WAKE_WINDOW_SAMPLES]).into())?
+            .into_optimized()?
+            .into_runnable()?;
+        Ok(Self { model })
+    }
+}
+
+impl KeywordSpotter for OnnxKeywordSpotter {
+    fn score(&mut self, window: &[f32]) -> Result<f32> {
+        let input = Tensor::from_shape(&[1, window.len()], window)?;
+        let outputs = self.model.run(tvec!(input.into()))?;
+        outputs[0]
+            .as_slice::<f32>()?
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow!("Wake-word model returned no output"))
+    }
+}
+
+/// A heard wake word.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WakeDetection {
+    pub score: f32,
+    /// Samples pushed after the wake word ended. Only these belong to the new
+    /// session; everything before them in the pre-roll is the wake word itself.
+    pub keep_samples: usize,
+}
+
+/// Runs a spotter over the stream and reports each utterance of the phrase once,
+/// at its best-scoring window.
+pub struct WakeWordDetector {
+    spotter: Box<dyn KeywordSpotter>,
+    threshold: f32,
+    // Last WAKE_WINDOW_SAMPLES samples, oldest first
+    window: Vec<f32>,
+    filled: usize,
+    since_score: usize,
+    // Above threshold: best score so far and samples pushed since that window
+    peak: Option<(f32, usize)>,
+    above_for: usize,
+}
+
+impl WakeWordDetector {
+    pub fn new(spotter: Box<dyn KeywordSpotter>, threshold: f32) -> Self {
+        Self {
+            spotter,
+            threshold,
+            window: vec![0.0; WAKE_WINDOW_SAMPLES],
+            filled: 0,
+            since_score: 0,
+            peak: None,
+            above_for: 0,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Forgets buffered audio, e.g. after a recording.
+    pub fn reset(&mut self) {
+        self.window.iter_mut().for_each(|s| *s = 0.0);
+        self.filled = 0;
+        self.since_score = 0;
+        self.peak = None;
+        self.above_for = 0;
+    }
+
+    /// Feeds 16 kHz mono samples.
+    pub fn push(&mut self, samples: &[f32]) -> Option<WakeDetection> {
+        let mut detection = None;
+        let mut rest = samples;
+        // Score at hop boundaries, which need not line up with the blocks
+        while !rest.is_empty() {
+            let take = rest.len().min(HOP_SAMPLES - self.since_score);
+            self.advance(&rest[..take]);
+            rest = &rest[take..];
+            if self.since_score == HOP_SAMPLES {
+                self.since_score = 0;
+                if let Some(d) = self.score_window(rest.len()) {
+                    detection = Some(d);
+                }
+            }
+        }
+        detection
+    }
+
+    fn advance(&mut self, samples: &[f32]) {
+        self.window.drain(..samples.len());
+        self.window.extend_from_slice(samples);
+        self.filled = (self.filled + samples.len()).min(WAKE_WINDOW_SAMPLES);
+        self.since_score += samples.len();
+        if let Some((_, since_peak)) = self.peak.as_mut() {
+            *since_peak += samples.len();
+        }
+    }
+
+    // `remaining`: samples of the current push still to come after this point
+    fn score_window(&mut self, remaining: usize) -> Option<WakeDetection> {
+        if self.filled < WAKE_WINDOW_SAMPLES {
+            return None;
+        }
+        let score = match self.spotter.score(&self.window) {
+            Ok(s) => s,
+            Err(e) => {
+                println!("[ERROR] Wake-word inference failed: {}", e);
+                return None;
+            }
+        };
+
+        if score >= self.threshold {
+            self.above_for += HOP_SAMPLES;
+            // Ties go to the later window, which holds more of the phrase's end
+            if self.peak.map_or(true, |(best, _)| score >= best) {
+                self.peak = Some((score, 0));
+            }
+            if self.above_for < MAX_PEAK_WAIT_SAMPLES {
+                return None;
+            }
+        }
+        // Fell below the threshold (or stayed above too long): the peak was the phrase
+        self.above_for = 0;
+        let (best, since_peak) = self.peak.take()?;
+        // The phrase must not trigger again from audio still in the window
+        self.window.iter_mut().for_each(|s| *s = 0.0);
+        self.filled = 0;
+        Some(WakeDetection {
+            score: best,
+            keep_samples: since_peak + remaining,
+        })
+    }
+}
+
+enum WakeJob {
+    Settings(WakeWordSettings),
+    Audio(SensitiveAudio),
+    Reset,
+}
+
+/// The pipeline's side of the wake word. Model loading and inference are far
+/// too slow for the audio callback, so a spotter thread owns the detector and
+/// this only queues audio and settings for it (never blocking) and picks up its
+/// detections. Also keeps quiet during the cooldown after a recording.
+pub struct WakeWordListener {
+    jobs: SyncSender<WakeJob>,
+    // Detections with the number of samples the thread had been given by then
+    detections: Receiver<(WakeDetection, usize)>,
+    // Settings last handed to the thread
+    sent_settings: Option<WakeWordSettings>,
+    // Samples handed to the thread so far, and that count at the last recording's end
+    sent_samples: usize,
+    reset_at: usize,
+    cooldown_left: usize,
+}
+
+impl WakeWordListener {
+    pub fn new(model_dir: PathBuf) -> Self {
+        let (jobs, queue) = mpsc::sync_channel(JOB_QUEUE_BLOCKS);
+        let (found, detections) = mpsc::channel();
+        thread::spawn(move || spot(queue, found, model_dir));
+        Self {
+            jobs,
+            detections,
+            sent_settings: None,
+            sent_samples: 0,
+            reset_at: 0,
+            cooldown_left: 0,
+        }
+    }
+
+    /// Called when a recording ends: drop what was heard and start the cooldown.
+    pub fn session_finished(&mut self, settings: &WakeWordSettings) {
+        let _ = self.jobs.try_send(WakeJob::Reset);
+        self.reset_at = self.sent_samples;
+        self.cooldown_left = settings.cooldown_ms as usize * SAMPLE_RATE / 1000;
+    }
+
+    /// Feeds 16 kHz mono audio heard between recordings. A detection arrives a
+    /// few blocks after its audio; `keep_samples` accounts for those.
+    pub fn push(&mut self, settings: &WakeWordSettings, samples: &[f32]) -> Option<WakeDetection> {
+        if self.sent_settings.as_ref() != Some(settings)
+            && self
+                .jobs
+                .try_send(WakeJob::Settings(settings.clone()))
+                .is_ok()
+        {
+            self.sent_settings = Some(settings.clone());
+        }
+        if !settings.enabled {
+            return None;
+        }
+        if self.cooldown_left > 0 {
+            self.cooldown_left = self.cooldown_left.saturating_sub(samples.len());
+            return None;
+        }
+
+        let audio = SensitiveAudio::new(samples.to_vec());
+        if self.jobs.try_send(WakeJob::Audio(audio)).is_ok() {
+            self.sent_samples += samples.len();
+        }
+        let mut heard = None;
+        while let Ok((detection, at)) = self.detections.try_recv() {
+            // Audio from before the last recording ended doesn't count
+            if at > self.reset_at {
+                heard = Some(WakeDetection {
+                    score: detection.score,
+                    keep_samples: detection.keep_samples + (self.sent_samples - at),
+                });
+            }
+        }
+        heard
+    }
+}
+
+// The spotter thread: ends when the listener (and with it the queue) is dropped
+fn spot(queue: Receiver<WakeJob>, found: mpsc::Sender<(WakeDetection, usize)>, model_dir: PathBuf) {
+    // Phrase the detector was loaded for (also set when loading failed, so a
+    // missing model is reported once)
+    let mut phrase: Option<String> = None;
+    let mut detector: Option<WakeWordDetector> = None;
+    let mut received = 0;
+    while let Ok(job) = queue.recv() {
+        match job {
+            WakeJob::Settings(settings) if !settings.enabled => {
+                if phrase.take().is_some() {
+                    println!("[DEBUG] Wake word disabled.");
+                    detector = None;
+                }
+            }
+            WakeJob::Settings(settings) => {
+                // Only a new phrase needs another model
+                if phrase.as_deref() != Some(settings.phrase.as_str()) {
+                    detector = load(&settings, &model_dir);
+                    phrase = Some(settings.phrase);
+                }
+                if let Some(detector) = detector.as_mut() {
+                    detector.set_threshold(settings.threshold);
+                }
+            }
+            WakeJob::Audio(audio) => {
+                received += audio.as_slice().len();
+                let heard = detector.as_mut().and_then(|d| d.push(audio.as_slice()));
+                if let Some(detection) = heard {
+                    if found.send((detection, received)).is_err() {
+                        break;
+                    }
+                }
+            }
+            WakeJob::Reset => {
+                if let Some(detector) = detector.as_mut() {
+                    detector.reset();
+                }
+            }
+        }
+    }
+}
+
+fn load(settings: &WakeWordSettings, model_dir: &Path) -> Option<WakeWordDetector> {
+    let path = model_path(model_dir, &settings.phrase);
+    match OnnxKeywordSpotter::load(&path) {
+        Ok(spotter) => {
+            println!(
+                "[DEBUG] Wake word \"{}\" loaded from {:?}",
+                settings.phrase, path
+            );
+            Some(WakeWordDetector::new(Box::new(spotter), settings.threshold))
+        }
+        Err(e) => {
+            println!("[WARNING] {}. Wake word stays off.", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use vibeflow_lib::modules::archive::{ArchiveSettings, SessionArchive, SessionMeta};
+use vibeflow_lib::modules::audio::SensitiveAudio;
+
+fn meta(transcript: &str) -> SessionMeta {
+    SessionMeta {
+        id: String::new(),
+        created_at: 0,
+        duration_ms: 0,
+        model: "ggml-base.en.bin".to_string(),
+        app_name: "Test App".to_string(),
+        mode: "Default".to_string(),
+        language: Some("en".to_string()),
+        translated: false,
+        transcript: transcript.to_string(),
+        refined: transcript.to_string(),
+    }
+}
+
+fn archive(name: &str, settings: ArchiveSettings) -> (std::path::PathBuf, SessionArchive) {
+    let dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    let archive = SessionArchive::new(&dir, settings);
+    (dir, archive)
+}
+
+#[test]
+fn test_archive_round_trip_is_encrypted_on_disk() {
+    let (dir, archive) = archive("vibeflow_archive_roundtrip", ArchiveSettings::default());
+    let samples: Vec<f32> = (0..16000).map(|i| (i as f32 * 0.01).sin()).collect();
+
+    let id = archive
+        .store(meta("secret meeting notes"), &SensitiveAudio::new(samples.clone()))
+        .unwrap();
+
+    // Nothing readable ends up on disk
+    for entry in std::fs::read_dir(dir.join("archive")).unwrap() {
+        let data = std::fs::read(entry.unwrap().path()).unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("secret meeting notes"));
+    }
+
+    let sessions = archive.list().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].transcript, "secret meeting notes");
+    assert_eq!(sessions[0].duration_ms, 1000);
+    assert_eq!(archive.load_audio(&id).unwrap().as_slice(), &samples[..]);
+
+    archive.delete(&id).unwrap();
+    assert!(archive.list().unwrap().is_empty());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_archive_retention_keeps_newest_sessions() {
+    let settings = ArchiveSettings {
+        enabled: true,
+        max_sessions: 2,
+        max_age_days: 0,
+        max_total_mb: 0,
+    };
+    let (dir, archive) = archive("vibeflow_archive_retention", settings);
+
+    for text in ["one", "two", "three"] {
+        // Back to back: several sessions may share a millisecond
+        archive.store(meta(text), &SensitiveAudio::new(vec![0.1; 1600])).unwrap();
+    }
+
+    let kept: Vec<String> = archive.list().unwrap().iter().map(|s| s.transcript.clone()).collect();
+    assert_eq!(kept, vec!["three", "two"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_sessions_in_the_same_millisecond_get_their_own_ids() {
+    let settings = ArchiveSettings {
+        enabled: true,
+        ..ArchiveSettings::default()
+    };
+    let (dir, archive) = archive("vibeflow_archive_same_ms", settings);
+
+    let audio = SensitiveAudio::new(vec![0.1; 160]);
+    let first = archive.store(meta("first"), &audio).unwrap();
+    let second = archive.store(meta("second"), &audio).unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(archive.list().unwrap().len(), 2);
+    assert_eq!(archive.load_audio(&second).unwrap().as_slice(), audio.as_slice());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_concurrent_first_stores_share_one_key() {
+    let settings = ArchiveSettings {
+        enabled: true,
+        ..ArchiveSettings::default()
+    };
+    let (dir, archive) = archive("vibeflow_archive_first_key", settings);
+
+    let threads = 16;
+    let start = std::sync::Barrier::new(threads);
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let (archive, start) = (&archive, &start);
+            scope.spawn(move || {
+                let audio = SensitiveAudio::new(vec![0.1; 160]);
+                start.wait();
+                archive.store(meta("dictation"), &audio).unwrap();
+            });
+        }
+    });
+
+    // Every session decrypts with the key that ended up on disk
+    assert_eq!(archive.list().unwrap().len(), threads);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_clear_destroys_the_key() {
+    let (dir, archive) = archive("vibeflow_archive_clear", ArchiveSettings::default());
+    let audio = SensitiveAudio::new(vec![0.1; 160]);
+    archive.store(meta("before"), &audio).unwrap();
+    let key_path = dir.join("archive").join("archive.key");
+    let old_key = std::fs::read(&key_path).unwrap();
+
+    archive.clear().unwrap();
+    assert!(!key_path.exists());
+    assert!(archive.list().unwrap().is_empty());
+
+    // A fresh key for what comes next
+    let id = archive.store(meta("after"), &audio).unwrap();
+    assert_ne!(std::fs::read(&key_path).unwrap(), old_key);
+    assert_eq!(
+        archive.load_audio(&id).unwrap().as_slice(),
+        audio.as_slice()
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,122 @@
+use parking_lot::Mutex;
+use vibeflow_lib::modules::calibration::{
+    record_phase, suggest, CalibrationIssue, CalibrationPhase, CalibrationProfiles,
+    PhaseMeasurement, PhaseMeter,
+};
+use vibeflow_lib::modules::handoff::audio_queue;
+use vibeflow_lib::modules::levels::GainSettings;
+use vibeflow_lib::modules::vad::VadSettings;
+
+// Noise at `noise` amplitude, with `speech` amplitude bursts in half of the 100 ms slots
+fn measure(phase: CalibrationPhase, noise: f32, speech: f32) -> PhaseMeasurement {
+    let mut meter = PhaseMeter::new(phase);
+    let mut seed = 12345u32;
+    for slot in 0..30 {
+        let level = if speech > 0.0 && slot % 2 == 0 { speech } else { noise };
+        let block: Vec<f32> = (0..1600)
+            .map(|i| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let n = (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0;
+                let tone = (2.0 * std::f32::consts::PI * 200.0 * i as f32 / 16000.0).sin();
+                noise * n + (level - noise).max(0.0) * tone
+            })
+            .collect();
+        // Odd push sizes: blocks must still be 10 ms
+        for chunk in block.chunks(333) {
+            meter.push(chunk);
+        }
+    }
+    meter.finish().unwrap()
+}
+
+#[test]
+fn test_phase_levels() {
+    let silence = measure(CalibrationPhase::Silence, 0.001, 0.0);
+    assert_eq!(silence.duration_ms, 3000);
+    // Uniform noise of amplitude a has RMS a / sqrt(3): -64.8 dBFS for 0.001
+    assert!((silence.p50_dbfs + 64.8).abs() < 1.5, "{:?}", silence);
+
+    let speech = measure(CalibrationPhase::Speech, 0.001, 0.1);
+    // A 0.1 sine is -23 dBFS; the quiet half of the slots must not drag p90 down
+    assert!((speech.p90_dbfs + 23.0).abs() < 1.5, "{:?}", speech);
+    assert!(speech.p10_dbfs < -60.0);
+    assert_eq!(speech.clipped_ratio, 0.0);
+}
+
+#[test]
+fn test_suggestions_follow_the_room() {
+    let gain = GainSettings::default();
+
+    // Quiet room, clear speech: gentle VAD, modest boost
+    let quiet = suggest(
+        "mic",
+        &measure(CalibrationPhase::Silence, 0.0005, 0.0),
+        &measure(CalibrationPhase::Speech, 0.0005, 0.1),
+        &gain,
+    );
+    assert!(quiet.issues.is_empty(), "{:?}", quiet.issues);
+    assert_eq!(quiet.profile.vad_aggressiveness, 1);
+    // target -20, speech -23, plus 6 dB headroom
+    assert_eq!(quiet.profile.max_gain_db, 9.0);
+
+    // Noisy room, soft speech: strict VAD and a low-SNR warning
+    let noisy = suggest(
+        "mic",
+        &measure(CalibrationPhase::Silence, 0.02, 0.0),
+        &measure(CalibrationPhase::Speech, 0.02, 0.05),
+        &gain,
+    );
+    assert_eq!(noisy.profile.vad_aggressiveness, 3);
+    assert!(noisy.issues.contains(&CalibrationIssue::LowSnr));
+
+    // Muted mic: nothing but the floor in both phases
+    let muted = suggest(
+        "mic",
+        &measure(CalibrationPhase::Silence, 0.001, 0.0),
+        &measure(CalibrationPhase::Speech, 0.001, 0.0),
+        &gain,
+    );
+    assert!(muted.issues.contains(&CalibrationIssue::NoSpeech));
+}
+
+#[test]
+fn test_profile_overrides_only_its_device() {
+    let result = suggest(
+        "alsa:sysdefault:CARD=Q9",
+        &measure(CalibrationPhase::Silence, 0.0005, 0.0),
+        &measure(CalibrationPhase::Speech, 0.0005, 0.1),
+        &GainSettings::default(),
+    );
+    let mut profiles = CalibrationProfiles::default();
+    profiles
+        .devices
+        .insert(result.device.clone(), result.profile.clone());
+
+    let gain = GainSettings::default();
+    let vad = VadSettings::default();
+    let q9_gain = profiles.gain_for("alsa:sysdefault:CARD=Q9", &gain);
+    assert_eq!(q9_gain.max_gain_db, result.profile.max_gain_db);
+    assert_eq!(q9_gain.target_dbfs, gain.target_dbfs);
+    assert_eq!(
+        profiles
+            .vad_for("alsa:sysdefault:CARD=Q9", &vad)
+            .aggressiveness,
+        result.profile.vad_aggressiveness
+    );
+    assert_eq!(profiles.gain_for("other", &gain).max_gain_db, gain.max_gain_db);
+    assert_eq!(profiles.vad_for("other", &vad).aggressiveness, vad.aggressiveness);
+}
+
+#[test]
+fn test_one_calibration_step_at_a_time() {
+    // Another step is measuring: this one is refused and leaves its tap alone
+    let (other, _other_rx) = audio_queue(16000);
+    let tap = Mutex::new(Some(other));
+    assert!(record_phase(CalibrationPhase::Silence, 1, &tap).is_err());
+    assert!(tap.lock().is_some());
+
+    // No stream fed the tap: the step fails, and still removes it
+    let tap = Mutex::new(None);
+    assert!(record_phase(CalibrationPhase::Silence, 1, &tap).is_err());
+    assert!(tap.lock().is_none());
+}
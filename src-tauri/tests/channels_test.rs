@@ -0,0 +1,76 @@
+use vibeflow_lib::modules::channels::{ChannelMixer, ChannelPolicy};
+
+const RATE: u32 = 48000;
+
+fn sine(freq: f32, amplitude: f32, frames: usize, delay: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| {
+            let t = i as f32 - delay as f32;
+            amplitude * (2.0 * std::f32::consts::PI * freq * t / RATE as f32).sin()
+        })
+        .collect()
+}
+
+fn interleave(channels: &[Vec<f32>]) -> Vec<f32> {
+    (0..channels[0].len())
+        .flat_map(|i| channels.iter().map(move |c| c[i]))
+        .collect()
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+fn mix_blocks(mixer: &mut ChannelMixer, data: &[f32], channels: usize, policy: ChannelPolicy) -> Vec<f32> {
+    data.chunks(480 * channels)
+        .flat_map(|block| mixer.mix(block, policy))
+        .collect()
+}
+
+#[test]
+fn test_specific_channel_ignores_other_inputs() {
+    // Mic on input 2, noise on input 1
+    let mic = sine(440.0, 0.5, RATE as usize, 0);
+    let noise = sine(3000.0, 0.2, RATE as usize, 0);
+    let data = interleave(&[noise, mic.clone()]);
+
+    let mut mixer = ChannelMixer::new(2, RATE);
+    let output = mix_blocks(&mut mixer, &data, 2, ChannelPolicy::Channel { index: 1 });
+    assert_eq!(output, mic);
+
+    // Average halves the mic level
+    let mut mixer = ChannelMixer::new(2, RATE);
+    let averaged = mix_blocks(&mut mixer, &data, 2, ChannelPolicy::Average);
+    assert!(rms(&averaged) < rms(&mic) * 0.8);
+}
+
+#[test]
+fn test_max_energy_follows_loudest_channel() {
+    let quiet = sine(440.0, 0.01, RATE as usize, 0);
+    let loud = sine(440.0, 0.5, RATE as usize, 0);
+    let data = interleave(&[quiet, loud]);
+
+    let mut mixer = ChannelMixer::new(2, RATE);
+    let output = mix_blocks(&mut mixer, &data, 2, ChannelPolicy::MaxEnergy);
+    let tail = &output[output.len() - 4800..];
+    assert!((rms(tail) - 0.5 / 2f32.sqrt()).abs() < 0.01, "rms {}", rms(tail));
+}
+
+#[test]
+fn test_delay_and_sum_avoids_phase_cancellation() {
+    // 5 samples at 48 kHz is half a period of 4.8 kHz: averaging cancels it
+    let delay = 5;
+    let a = sine(4800.0, 0.5, RATE as usize, 0);
+    let b = sine(4800.0, 0.5, RATE as usize, delay);
+    let data = interleave(&[a, b]);
+
+    let mut mixer = ChannelMixer::new(2, RATE);
+    let averaged = mix_blocks(&mut mixer, &data, 2, ChannelPolicy::Average);
+    let mut mixer = ChannelMixer::new(2, RATE);
+    let aligned = mix_blocks(&mut mixer, &data, 2, ChannelPolicy::DelayAndSum);
+
+    let tail = |v: &[f32]| rms(&v[v.len() - 4800..]);
+    assert!(tail(&averaged) < 0.05);
+    assert!(tail(&aligned) > 0.33);
+    assert_eq!(aligned.len(), averaged.len());
+}
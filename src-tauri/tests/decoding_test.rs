@@ -0,0 +1,87 @@
+use vibeflow_lib::modules::decoding::{DecodingProfile, DecodingSettings, Strategy};
+
+#[test]
+fn test_defaults_match_previous_decoding() {
+    let settings = DecodingSettings::default();
+    assert_eq!(settings.partial, settings.final_pass);
+    assert_eq!(settings.partial.strategy, Strategy::Greedy { best_of: 1 });
+    assert_eq!(settings.partial.threads, 0);
+    assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn test_config_json() {
+    let json = serde_json::json!({
+        "partial": { "threads": 4, "temperature_increment": 0.0 },
+        "final": {
+            "strategy": { "type": "beam_search", "beam_size": 5 },
+            "no_speech_threshold": 0.4
+        }
+    });
+    let settings: DecodingSettings = serde_json::from_value(json).unwrap();
+
+    assert_eq!(settings.partial.threads, 4);
+    assert_eq!(settings.partial.temperature_increment, 0.0);
+    // Unset fields keep their defaults
+    assert_eq!(settings.partial.strategy, Strategy::Greedy { best_of: 1 });
+    assert_eq!(
+        settings.final_pass.strategy,
+        Strategy::BeamSearch { beam_size: 5 }
+    );
+    assert_eq!(settings.final_pass.no_speech_threshold, 0.4);
+    assert_eq!(settings.final_pass.entropy_threshold, 2.4);
+
+    let round_trip: DecodingSettings = serde_json::from_value(serde_json::json!(settings)).unwrap();
+    assert_eq!(round_trip, settings);
+}
+
+#[test]
+fn test_invalid_profiles_are_rejected() {
+    let invalid = [
+        DecodingProfile {
+            strategy: Strategy::BeamSearch { beam_size: 0 },
+            ..Default::default()
+        },
+        DecodingProfile {
+            strategy: Strategy::Greedy { best_of: 9 },
+            ..Default::default()
+        },
+        DecodingProfile {
+            temperature: 1.5,
+            ..Default::default()
+        },
+        DecodingProfile {
+            entropy_threshold: 0.0,
+            ..Default::default()
+        },
+        DecodingProfile {
+            logprob_threshold: 0.5,
+            ..Default::default()
+        },
+        DecodingProfile {
+            no_speech_threshold: f32::NAN,
+            ..Default::default()
+        },
+        DecodingProfile {
+            threads: 65,
+            ..Default::default()
+        },
+    ];
+    for profile in invalid {
+        assert!(profile.validate().is_err(), "{:?}", profile);
+    }
+    let most_threads = DecodingProfile {
+        threads: 64,
+        ..Default::default()
+    };
+    assert!(most_threads.validate().is_ok());
+
+    let settings = DecodingSettings {
+        final_pass: DecodingProfile {
+            temperature_increment: -0.2,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(settings.validate().unwrap_err().starts_with("Final pass"));
+}
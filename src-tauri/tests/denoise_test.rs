@@ -0,0 +1,79 @@
+use vibeflow_lib::modules::denoise::{DenoiseSettings, Denoiser, DENOISE_SAMPLE_RATE};
+use vibeflow_lib::modules::resampler::StreamResampler;
+
+// RNNoise frame: 10 ms at 48 kHz
+const FRAME: usize = 480;
+
+fn sine(rate: u32, freq: f32, seconds: f32) -> Vec<f32> {
+    (0..(rate as f32 * seconds) as usize)
+        .map(|i| 0.3 * (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin())
+        .collect()
+}
+
+#[test]
+fn test_output_comes_in_whole_frames() {
+    let mut denoiser = Denoiser::new();
+    let input = sine(DENOISE_SAMPLE_RATE, 440.0, 0.1);
+
+    // Callback-sized chunks that don't line up with the 480-sample frames
+    let mut output = Vec::new();
+    let mut fed = 0;
+    for chunk in input.chunks(333) {
+        let out = denoiser.process(chunk);
+        fed += chunk.len();
+        assert_eq!(out.len() % FRAME, 0);
+        output.extend(out);
+        // At most one partial frame is held back
+        assert_eq!(output.len(), fed / FRAME * FRAME);
+    }
+
+    // The first frame (RNNoise fade-in) is silenced, not dropped
+    assert!(output[..FRAME].iter().all(|&s| s == 0.0));
+    assert!(output.iter().all(|s| s.is_finite() && s.abs() <= 1.5));
+}
+
+#[test]
+fn test_reset_drops_partial_frame_and_mutes_the_next() {
+    let mut denoiser = Denoiser::new();
+    denoiser.process(&sine(DENOISE_SAMPLE_RATE, 440.0, 0.05));
+    denoiser.process(&[0.1; FRAME / 2]);
+    denoiser.reset();
+
+    let out = denoiser.process(&sine(DENOISE_SAMPLE_RATE, 440.0, 0.02));
+    assert_eq!(out.len(), 2 * FRAME);
+    assert!(out[..FRAME].iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn test_48k_round_trip_keeps_the_timing() {
+    // The pipeline's denoise path: 16 kHz device -> 48 kHz -> RNNoise -> 16 kHz
+    let mut up = StreamResampler::new(16000, DENOISE_SAMPLE_RATE).unwrap();
+    let mut down = StreamResampler::new(DENOISE_SAMPLE_RATE, 16000).unwrap();
+    let mut denoiser = Denoiser::new();
+
+    let input = sine(16000, 300.0, 2.0);
+    let mut output = Vec::new();
+    for block in input.chunks(160) {
+        let upsampled = up.process(block);
+        output.extend(down.process(&denoiser.process(&upsampled)));
+    }
+
+    // Same length, give or take one RNNoise frame and the resampler delays
+    let lost = input.len() - output.len();
+    assert!(
+        lost <= 16000 / 100 + 400,
+        "round trip lost {} samples",
+        lost
+    );
+    assert!(output.iter().all(|s| s.is_finite()));
+}
+
+#[test]
+fn test_per_device_switch() {
+    let mut settings = DenoiseSettings::default();
+    assert!(!settings.enabled_for("USB Mic"));
+    settings.devices.insert("USB Mic".into(), true);
+    settings.default_enabled = false;
+    assert!(settings.enabled_for("USB Mic"));
+    assert!(!settings.enabled_for("Webcam"));
+}
@@ -0,0 +1,131 @@
+use serde_json::json;
+use vibeflow_lib::modules::devices::{
+    device_id, display_name, migrate_device_config, migrate_device_settings, rates_in_ranges,
+    resolve, settings_key_id, DeviceInfo, DeviceKind,
+};
+
+fn device(host: &str, raw_name: &str) -> DeviceInfo {
+    DeviceInfo {
+        id: device_id(host, raw_name),
+        raw_name: raw_name.to_string(),
+        display_name: display_name(raw_name),
+        host: host.to_string(),
+        channels: vec![1, 2],
+        stream_channels: 2,
+        sample_rates: vec![44100, 48000],
+        sample_formats: vec!["f32".to_string()],
+        is_default: false,
+        kind: DeviceKind::Microphone,
+    }
+}
+
+#[test]
+fn test_display_names_and_resolution() {
+    let devices = vec![
+        device("ALSA", "default"),
+        device("ALSA", "sysdefault:CARD=Q9"),
+        device("ALSA", "sysdefault:CARD=PCH"),
+        device("ALSA", "front:CARD=PCH,DEV=0"),
+    ];
+    assert_eq!(devices[1].id, "alsa:sysdefault:CARD=Q9");
+    assert_eq!(devices[1].display_name, "Q9 Microphone 🎙️");
+    assert_eq!(devices[2].display_name, "PCH");
+
+    // Id, raw name and the old picker's display name all find the device
+    assert_eq!(
+        resolve("alsa:sysdefault:CARD=PCH", &devices),
+        Some(&devices[2])
+    );
+    assert_eq!(resolve("front:CARD=PCH,DEV=0", &devices), Some(&devices[3]));
+    assert_eq!(resolve("Q9 Microphone 🎙️", &devices), Some(&devices[1]));
+    assert_eq!(resolve("PCH", &devices), Some(&devices[2]));
+    assert_eq!(resolve("USB Headset", &devices), None);
+}
+
+#[test]
+fn test_legacy_device_config_migrates_to_id() {
+    let devices = vec![
+        device("ALSA", "default"),
+        device("ALSA", "sysdefault:CARD=Q9"),
+    ];
+
+    // The old UI stored display names, which start_stream could not find
+    let mut config = json!({ "model": "ggml-base.en.bin", "device": "Q9 Microphone 🎙️" });
+    assert!(migrate_device_config(&mut config, &devices));
+    assert_eq!(config["device_id"], "alsa:sysdefault:CARD=Q9");
+    assert!(config.get("device").is_none());
+    assert_eq!(config["model"], "ggml-base.en.bin");
+
+    // Already migrated: nothing to do
+    assert!(!migrate_device_config(&mut config, &devices));
+
+    // Device not plugged in: left alone for the next start
+    let mut config = json!({ "device": "USB Headset" });
+    assert!(!migrate_device_config(&mut config, &devices));
+    assert_eq!(config["device"], "USB Headset");
+    assert!(config.get("device_id").is_none());
+}
+
+#[test]
+fn test_legacy_device_settings_rekey_by_id() {
+    assert_eq!(
+        settings_key_id("sysdefault:CARD=Q9", "ALSA").as_deref(),
+        Some("alsa:sysdefault:CARD=Q9")
+    );
+    assert_eq!(
+        settings_key_id("alsa_output.pci.analog-stereo.monitor", "ALSA").as_deref(),
+        Some("loopback:alsa_output.pci.analog-stereo.monitor")
+    );
+    assert_eq!(settings_key_id("alsa:default", "ALSA"), None);
+    assert_eq!(settings_key_id("loopback:x.monitor", "ALSA"), None);
+
+    let mut config = json!({
+        "channels": {
+            "default_policy": "average",
+            "devices": { "sysdefault:CARD=Q9": "max_energy" }
+        },
+        // Works for devices that are not plugged in, too
+        "denoise": { "devices": { "USB Headset": true, "alsa:default": false } },
+        "calibration": {
+            "devices": {
+                "default": { "quiet_dbfs": -50.0 },
+                "alsa:default": { "quiet_dbfs": -40.0 }
+            }
+        }
+    });
+    assert!(migrate_device_settings(&mut config, "ALSA"));
+    assert_eq!(
+        config["channels"]["devices"],
+        json!({ "alsa:sysdefault:CARD=Q9": "max_energy" })
+    );
+    assert_eq!(config["channels"]["default_policy"], "average");
+    assert_eq!(
+        config["denoise"]["devices"],
+        json!({ "alsa:USB Headset": true, "alsa:default": false })
+    );
+    // An entry already stored under the id wins
+    assert_eq!(
+        config["calibration"]["devices"],
+        json!({ "alsa:default": { "quiet_dbfs": -40.0 } })
+    );
+
+    // Runs once
+    assert!(!migrate_device_settings(&mut config, "ALSA"));
+    assert!(!migrate_device_settings(
+        &mut json!({ "model": "x" }),
+        "ALSA"
+    ));
+}
+
+#[test]
+fn test_rates_in_ranges() {
+    assert_eq!(rates_in_ranges(&[(48000, 48000)]), vec![48000]);
+    assert_eq!(
+        rates_in_ranges(&[(8000, 48000), (44100, 44100)]),
+        vec![8000, 11025, 16000, 22050, 32000, 44100, 48000]
+    );
+    assert_eq!(
+        rates_in_ranges(&[(4000, 12000)]),
+        vec![4000, 8000, 11025, 12000]
+    );
+}
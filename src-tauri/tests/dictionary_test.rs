@@ -0,0 +1,127 @@
+use vibeflow_lib::modules::dictionary::{initial_prompt, Dictionary, DictionaryScope};
+use vibeflow_lib::modules::llm::AppMode;
+
+fn dictionary() -> Dictionary {
+    Dictionary {
+        words: vec!["VibeFlow".into(), "Jannik".into()],
+        scopes: vec![
+            DictionaryScope {
+                app: None,
+                mode: Some(AppMode::Coding),
+                words: vec!["serde_json".into(), "VibeFlow".into()],
+            },
+            DictionaryScope {
+                app: Some("Slack".into()),
+                mode: None,
+                words: vec!["OKR".into()],
+            },
+            DictionaryScope {
+                app: Some("Code".into()),
+                mode: Some(AppMode::Chat),
+                words: vec!["standup".into()],
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_words_depend_on_app_and_mode() {
+    let d = dictionary();
+    assert_eq!(
+        d.words_for("Notepad", &AppMode::Default),
+        ["VibeFlow", "Jannik"]
+    );
+    // Scoped words first, without repeating global ones
+    assert_eq!(
+        d.words_for("Visual Studio Code", &AppMode::Coding),
+        ["serde_json", "VibeFlow", "Jannik"]
+    );
+    assert_eq!(
+        d.words_for("slack.exe", &AppMode::Chat),
+        ["OKR", "VibeFlow", "Jannik"]
+    );
+    // App and mode both set: both have to match
+    assert_eq!(
+        d.words_for("Visual Studio Code", &AppMode::Chat),
+        ["standup", "VibeFlow", "Jannik"]
+    );
+    assert_eq!(
+        d.words_for("Discord", &AppMode::Chat),
+        ["VibeFlow", "Jannik"]
+    );
+
+    assert_eq!(
+        d.vocabulary("Notepad", &AppMode::Default),
+        "VibeFlow, Jannik."
+    );
+    assert_eq!(
+        Dictionary::default().vocabulary("Notepad", &AppMode::Default),
+        ""
+    );
+}
+
+#[test]
+fn test_vocabulary_is_capped() {
+    let d = Dictionary {
+        words: (0..200).map(|i| format!("Word{}", i)).collect(),
+        scopes: Vec::new(),
+    };
+    let vocabulary = d.vocabulary("Notepad", &AppMode::Default);
+    assert!(vocabulary.len() <= 400);
+    assert!(vocabulary.starts_with("Word0, Word1, "));
+    assert!(vocabulary.ends_with('.'));
+}
+
+#[test]
+fn test_prompt_puts_vocabulary_before_context() {
+    assert_eq!(initial_prompt("", ""), "");
+    assert_eq!(initial_prompt("VibeFlow.", ""), "VibeFlow.");
+    assert_eq!(initial_prompt("", "So far"), "So far");
+    assert_eq!(initial_prompt("VibeFlow.", "So far"), "VibeFlow. So far");
+}
+
+#[test]
+fn test_add_and_remove_words() {
+    let mut d = Dictionary::default();
+    d.add(" Kubernetes ", None, None);
+    d.add("kubernetes", None, None);
+    assert_eq!(d.words, ["Kubernetes"]);
+
+    d.add("HashMap", None, Some(AppMode::Coding));
+    d.add("Vec", None, Some(AppMode::Coding));
+    d.add("standup", Some("Slack"), None);
+    assert_eq!(d.scopes.len(), 2);
+    assert_eq!(d.scopes[0].words, ["HashMap", "Vec"]);
+
+    // Only from the list it was added to
+    assert!(!d.remove("hashmap", None, None));
+    assert!(d.remove("hashmap", None, Some(AppMode::Coding)));
+    assert!(d.remove("STANDUP", Some("slack"), None));
+    // The emptied Slack scope is gone
+    assert_eq!(d.scopes.len(), 1);
+    assert_eq!(d.scopes[0].words, ["Vec"]);
+}
+
+#[test]
+fn test_normalized_and_round_trip() {
+    let d = Dictionary {
+        words: vec!["  API ".into(), "".into(), "api".into()],
+        scopes: vec![DictionaryScope {
+            app: Some("  ".into()),
+            mode: Some(AppMode::Terminal),
+            words: vec!["kubectl".into()],
+        }],
+    }
+    .normalized();
+    assert_eq!(d.words, ["API"]);
+    assert_eq!(d.scopes[0].app, None);
+
+    let dir = std::env::temp_dir().join(format!("vibeflow_dictionary_{}", std::process::id()));
+    let path = dir.join("dictionary.json");
+    d.save(&path).unwrap();
+    assert_eq!(Dictionary::load(&path), d);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Missing file: empty dictionary
+    assert_eq!(Dictionary::load(&path), Dictionary::default());
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+use vibeflow_lib::modules::audio::SensitiveAudio;
+use vibeflow_lib::modules::handoff::audio_queue;
+
+// 10 ms blocks, like the audio callback after resampling
+const BLOCK: usize = 160;
+
+fn block(index: usize) -> SensitiveAudio {
+    SensitiveAudio::new((0..BLOCK).map(|i| (index * BLOCK + i) as f32).collect())
+}
+
+#[tokio::test]
+async fn test_slow_consumer_loses_nothing() {
+    let (tx, mut rx) = audio_queue(16000 * 60);
+
+    // 3 s of audio pushed from a "realtime" thread: 300 blocks, three times what
+    // the old 100-slot channel could hold while the consumer was busy
+    let producer = std::thread::spawn(move || {
+        for i in 0..300 {
+            assert!(tx.push(block(i)), "block {} dropped", i);
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        tx.stats()
+    });
+
+    // Consumer stalls on the first block, like a long partial Whisper pass
+    let first = rx.recv().await.expect("no audio");
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    let mut received: Vec<f32> = first.as_slice().to_vec();
+    while let Some(chunk) = rx.recv().await {
+        received.extend_from_slice(chunk.as_slice());
+    }
+    let producer_stats = producer.join().unwrap();
+
+    // Every sample, in order
+    assert_eq!(received.len(), 300 * BLOCK);
+    assert!(received.iter().enumerate().all(|(i, &s)| s == i as f32));
+
+    let stats = rx.stats();
+    assert_eq!(stats.dropped_samples, 0);
+    assert_eq!(stats.delivered_samples, (300 * BLOCK) as u64);
+    assert_eq!(stats.queued_samples, 0);
+    assert!(stats.late_samples > 0, "stalled audio should count as late");
+    assert!(stats.max_delay_ms >= 1000);
+    assert!(stats.max_queued_samples > (100 * BLOCK) as u64);
+    assert!(producer_stats.max_queued_samples > 0);
+}
+
+#[tokio::test]
+async fn test_backlog_cap_drops_and_counts() {
+    let (tx, mut rx) = audio_queue(10 * BLOCK);
+
+    for i in 0..15 {
+        tx.push(block(i));
+    }
+    let stats = tx.stats();
+    assert_eq!(stats.queued_samples, (10 * BLOCK) as u64);
+    assert_eq!(stats.dropped_samples, (5 * BLOCK) as u64);
+
+    // Room again once the consumer catches up
+    drop(rx.recv().await);
+    assert!(tx.push(block(15)));
+
+    drop(tx);
+    let mut blocks = 0;
+    while rx.recv().await.is_some() {
+        blocks += 1;
+    }
+    assert_eq!(blocks, 10);
+    assert_eq!(rx.stats().delivered_samples, (11 * BLOCK) as u64);
+}
@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+use vibeflow_lib::modules::hotkeys::{
+    HotkeyAction, HotkeyBehavior, HotkeyController, HotkeyMode, RecordingSessions,
+};
+
+fn push_to_talk() -> HotkeyBehavior {
+    HotkeyBehavior {
+        mode: HotkeyMode::PushToTalk,
+        min_hold_ms: 300,
+    }
+}
+
+#[test]
+fn test_toggle_ignores_release() {
+    let mut c = HotkeyController::default();
+    let b = HotkeyBehavior::default();
+    let t = Instant::now();
+
+    assert_eq!(c.handle(b, true, false, t), HotkeyAction::Start);
+    assert_eq!(c.handle(b, false, true, t), HotkeyAction::Ignore);
+    assert_eq!(c.handle(b, true, true, t), HotkeyAction::Stop);
+}
+
+#[test]
+fn test_push_to_talk_hold_and_tap() {
+    let mut c = HotkeyController::default();
+    let b = push_to_talk();
+    let t = Instant::now();
+
+    // Held long enough: stop and transcribe; key repeats in between are ignored
+    assert_eq!(c.handle(b, true, false, t), HotkeyAction::Start);
+    assert_eq!(c.handle(b, true, true, t + Duration::from_millis(100)), HotkeyAction::Ignore);
+    assert_eq!(c.handle(b, false, true, t + Duration::from_millis(800)), HotkeyAction::Stop);
+
+    // Accidental tap: discard
+    let t = t + Duration::from_secs(2);
+    assert_eq!(c.handle(b, true, false, t), HotkeyAction::Start);
+    assert_eq!(c.handle(b, false, true, t + Duration::from_millis(120)), HotkeyAction::Discard);
+}
+
+#[test]
+fn test_push_to_talk_recording_ended_elsewhere() {
+    let mut c = HotkeyController::default();
+    let b = push_to_talk();
+    let t = Instant::now();
+
+    // Auto-stop fired while held: the release does nothing
+    assert_eq!(c.handle(b, true, false, t), HotkeyAction::Start);
+    assert_eq!(c.handle(b, false, false, t + Duration::from_secs(1)), HotkeyAction::Ignore);
+
+    // A recording started from the tray is ended by pressing the chord
+    assert_eq!(c.handle(b, true, true, t), HotkeyAction::Stop);
+    assert_eq!(c.handle(b, false, false, t), HotkeyAction::Ignore);
+}
+
+#[test]
+fn test_behavior_from_hotkey_entry() {
+    let entry = serde_json::json!({
+        "modifiers": ["CTRL", "SHIFT"],
+        "code": "SPACE",
+        "mode": "push_to_talk"
+    });
+    let b: HotkeyBehavior = serde_json::from_value(entry).unwrap();
+    assert_eq!(b.mode, HotkeyMode::PushToTalk);
+    assert_eq!(b.min_hold_ms, 300);
+
+    // Entries from before push-to-talk keep toggling
+    let old: HotkeyBehavior =
+        serde_json::from_value(serde_json::json!({"modifiers": [], "code": "F9"})).unwrap();
+    assert_eq!(old.mode, HotkeyMode::Toggle);
+}
+
+#[test]
+fn test_discard_only_hits_its_own_session() {
+    let mut sessions = RecordingSessions::default();
+    let tap = sessions.start();
+    sessions.discard_current();
+    assert!(sessions.is_discarded(tap));
+
+    // The tap produced no transcript, the next dictation must still go through
+    let next = sessions.start();
+    assert!(!sessions.is_discarded(next));
+    assert!(sessions.is_discarded(tap));
+}
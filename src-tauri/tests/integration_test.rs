@@ -0,0 +1,86 @@
+use parking_lot::Mutex;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use vibeflow_lib::modules::{
+    audio::AudioEngine,
+    decoding::DecodingSettings,
+    dictionary::Dictionary,
+    handoff::{audio_queue, DEFAULT_CAPACITY_SAMPLES},
+    inference::InferenceEngine,
+    language::LanguageSettings,
+    llm::ContextEngine,
+    model_manager::{ModelManager, ModelSettings},
+    os_integration::OSIntegration,
+    transcript::SensitiveTranscript,
+    vad::VadSettings,
+};
+
+const MODEL: &str = "ggml-base.en.bin";
+
+#[tokio::test]
+async fn test_e2e_pipeline() {
+    env::set_var("VIBEFLOW_TEST_MODE", "1");
+
+    // 1. Audio-Loopback Simulation
+    let (tx, mut rx) = audio_queue(DEFAULT_CAPACITY_SAMPLES);
+    let sim_handle = tokio::spawn(async move {
+        AudioEngine::simulate_recording(tx, 5000).await;
+    });
+    let mut samples = Vec::new();
+    while let Some(chunk) = rx.recv().await {
+        samples.extend_from_slice(chunk.as_slice());
+    }
+    sim_handle.await.unwrap();
+    assert_eq!(samples.len(), 16000 * 5, "Simulated recording lost audio!");
+    assert_eq!(rx.stats().dropped_samples, 0);
+
+    // 2. Inference Benchmark: needs a downloaded model, from
+    // VIBEFLOW_MODEL_DIR (default: the current directory)
+    let model_dir = env::var("VIBEFLOW_MODEL_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."));
+    let engine = InferenceEngine::new(
+        ModelManager::new(
+            model_dir,
+            Arc::new(Mutex::new(MODEL.to_string())),
+            Arc::new(Mutex::new(ModelSettings::default())),
+        ),
+        Arc::new(Mutex::new(LanguageSettings::default())),
+        Arc::new(Mutex::new(Dictionary::default())),
+        Arc::new(Mutex::new(DecodingSettings::default())),
+        Arc::new(Mutex::new(VadSettings::default())),
+    );
+    let inference_start = Instant::now();
+    let transcript = match engine.transcribe(MODEL, &samples) {
+        Ok(transcript) => {
+            // Faster than real time, or dictation falls behind
+            assert!(
+                inference_start.elapsed().as_millis() < 5000,
+                "Inference slower than real time!"
+            );
+            transcript
+        }
+        Err(e) => {
+            println!(" -> Inference Skipped ({})", e);
+            SensitiveTranscript::new("ähm Simulated transcript".to_string())
+        }
+    };
+
+    // 3. LLM Validation: falls back to the raw transcript when Ollama is offline
+    let (refined_result, _command) = ContextEngine::refine_text(&transcript)
+        .await
+        .unwrap_or_else(|_| (transcript.as_str().to_string(), None));
+    if refined_result != transcript.as_str() {
+        assert!(
+            !refined_result.to_lowercase().contains("ähm"),
+            "Filter failed to remove filler words!"
+        );
+    }
+
+    // 4. Input Sim
+    OSIntegration::paste_text(&refined_result).unwrap();
+    let pasted = OSIntegration::get_mock_paste();
+    assert_eq!(pasted, refined_result, "Clipboard/Paste simulation mismatch!");
+}
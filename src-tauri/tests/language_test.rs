@@ -0,0 +1,49 @@
+use vibeflow_lib::modules::language::{
+    effective_language, is_english_only, LanguageRule, LanguageSettings,
+};
+
+fn settings() -> LanguageSettings {
+    LanguageSettings {
+        language: "auto".to_string(),
+        rules: vec![
+            LanguageRule {
+                app: "Telegram".to_string(),
+                language: "de".to_string(),
+            },
+            LanguageRule {
+                app: "code".to_string(),
+                language: "en".to_string(),
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_rules_match_app_names_case_insensitively_in_order() {
+    let s = settings();
+    assert_eq!(s.language_for("telegram desktop"), "de");
+    assert_eq!(s.language_for("Visual Studio Code"), "en");
+    assert_eq!(s.language_for("Firefox"), "auto");
+}
+
+#[test]
+fn test_old_configs_default_to_english_without_rules() {
+    let s: LanguageSettings = serde_json::from_str("{}").unwrap();
+    assert_eq!(s, LanguageSettings::default());
+    assert_eq!(s.language_for("Slack"), "en");
+
+    let s: LanguageSettings = serde_json::from_str(r#"{"language": "fr"}"#).unwrap();
+    assert_eq!(s.language_for("Slack"), "fr");
+    assert!(s.rules.is_empty());
+}
+
+#[test]
+fn test_english_only_models_are_held_to_english() {
+    assert!(is_english_only("ggml-base.en.bin"));
+    assert!(!is_english_only("ggml-large-v3-turbo.bin"));
+    assert_eq!(effective_language("ggml-small.en.bin", "auto"), "en");
+    assert_eq!(effective_language("ggml-small.en.bin", "de"), "en");
+    assert_eq!(effective_language("ggml-small.bin", "auto"), "auto");
+    assert_eq!(effective_language("ggml-small.bin", "de"), "de");
+}
@@ -0,0 +1,92 @@
+use vibeflow_lib::modules::levels::{to_dbfs, GainSettings, LevelMonitor, LevelWarning};
+
+fn sine(amplitude: f32, seconds: f32) -> Vec<f32> {
+    (0..(16000.0 * seconds) as usize)
+        .map(|i| amplitude * (2.0 * std::f32::consts::PI * 300.0 * i as f32 / 16000.0).sin())
+        .collect()
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+// Runs `input` through AGC in 10 ms blocks, returns the processed audio
+fn run_agc(monitor: &mut LevelMonitor, input: &[f32], settings: &GainSettings) -> Vec<f32> {
+    let mut output = Vec::with_capacity(input.len());
+    for block in input.chunks(160) {
+        let mut block = block.to_vec();
+        monitor.apply_gain(&mut block, settings);
+        output.extend(block);
+    }
+    output
+}
+
+fn agc_on() -> GainSettings {
+    GainSettings {
+        agc: true,
+        ..GainSettings::default()
+    }
+}
+
+#[test]
+fn test_agc_is_opt_in() {
+    let settings = GainSettings::default();
+    assert!(!settings.agc);
+    let mut monitor = LevelMonitor::new();
+    let input = sine(0.01, 2.0);
+    assert_eq!(run_agc(&mut monitor, &input, &settings), input);
+}
+
+#[test]
+fn test_agc_brings_quiet_and_loud_input_to_target() {
+    let settings = agc_on();
+    for amplitude in [0.01, 0.8] {
+        let mut monitor = LevelMonitor::new();
+        let output = run_agc(&mut monitor, &sine(amplitude, 8.0), &settings);
+
+        // Judge the last second, after the release time has passed
+        let level = to_dbfs(rms(&output[output.len() - 16000..]));
+        assert!(
+            (level - settings.target_dbfs).abs() < 2.0,
+            "AGC missed the target for amplitude {} ({:.1} dBFS)",
+            amplitude,
+            level
+        );
+        assert!(output.iter().all(|x| x.abs() < 1.0), "AGC output clipped");
+    }
+}
+
+#[test]
+fn test_agc_does_not_boost_room_noise() {
+    let settings = agc_on();
+    let mut monitor = LevelMonitor::new();
+    // -66 dBFS RMS, below the noise gate
+    let output = run_agc(&mut monitor, &sine(0.0007, 3.0), &settings);
+    assert!(rms(&output) < 0.001, "Noise floor was amplified");
+}
+
+#[test]
+fn test_clipping_and_quiet_warnings() {
+    let settings = GainSettings::default();
+
+    let mut monitor = LevelMonitor::new();
+    monitor.start_session();
+    let clipped: Vec<f32> = sine(1.5, 2.0).iter().map(|x| x.clamp(-1.0, 1.0)).collect();
+    let warnings: Vec<LevelWarning> = clipped
+        .chunks(160)
+        .filter_map(|b| monitor.observe_input(b, rms(b), 16000.0, &settings))
+        .collect();
+    assert_eq!(warnings, vec![LevelWarning::Clipping]);
+    let stats = monitor.finish_session();
+    assert!(stats.clipped_ratio > 0.1);
+
+    let mut monitor = LevelMonitor::new();
+    monitor.start_session();
+    let quiet = sine(0.003, 3.0);
+    let warnings: Vec<LevelWarning> = quiet
+        .chunks(160)
+        .filter_map(|b| monitor.observe_input(b, rms(b), 16000.0, &settings))
+        .collect();
+    assert_eq!(warnings, vec![LevelWarning::TooQuiet]);
+    assert!(monitor.finish_session().max_block_rms_dbfs < settings.quiet_dbfs);
+}
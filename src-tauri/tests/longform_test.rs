@@ -0,0 +1,83 @@
+use vibeflow_lib::modules::longform::{
+    segment_cut, PauseTracker, HARD_SEGMENT_SAMPLES, OVERLAP_SAMPLES, SOFT_SEGMENT_SAMPLES,
+};
+use vibeflow_lib::modules::vad::VoiceActivityDetector;
+
+/// Any frame louder than 0.1 is speech.
+struct LoudnessVad;
+
+impl VoiceActivityDetector for LoudnessVad {
+    fn frame_size(&self) -> usize {
+        160
+    }
+    fn is_speech(&mut self, frame: &[f32]) -> bool {
+        frame.iter().any(|s| s.abs() > 0.1)
+    }
+    fn reset(&mut self) {}
+}
+
+fn speech(secs: f32) -> Vec<f32> {
+    vec![0.5; (secs * 16000.0) as usize]
+}
+
+fn silence(secs: f32) -> Vec<f32> {
+    vec![0.0; (secs * 16000.0) as usize]
+}
+
+#[test]
+fn test_pauses_are_found_but_gaps_between_words_are_not() {
+    let mut pauses = PauseTracker::new(Box::new(LoudnessVad));
+    pauses.push(&speech(2.0));
+    pauses.push(&silence(0.1));
+    pauses.push(&speech(2.0));
+    assert_eq!(pauses.last_pause(0, 100_000), None);
+
+    pauses.push(&silence(0.5));
+    // Counts while it is still going on
+    assert_eq!(pauses.last_pause(0, 100_000), Some(69600));
+    pauses.push(&speech(1.0));
+    assert_eq!(pauses.last_pause(0, 100_000), Some(69600));
+    assert_eq!(pauses.last_pause(80_000, 100_000), None);
+
+    pauses.forget_before(80_000);
+    assert_eq!(pauses.last_pause(0, 100_000), None);
+    // Speech heard in total, pauses aside
+    assert_eq!(pauses.speech_samples(), 80_000);
+}
+
+#[test]
+fn test_segments_end_at_the_last_pause_once_long_enough() {
+    let mut pauses = PauseTracker::new(Box::new(LoudnessVad));
+    pauses.push(&speech(6.0));
+    pauses.push(&silence(0.4));
+    pauses.push(&speech(6.0));
+    pauses.push(&silence(0.4));
+    pauses.push(&speech(2.0));
+    let total = pauses_total(&[6.0, 0.4, 6.0, 0.4, 2.0]);
+
+    // Too short to cut yet
+    assert_eq!(segment_cut(0, total, &pauses), None);
+
+    pauses.push(&speech(2.0));
+    let total = total + 32000;
+    assert!(total >= SOFT_SEGMENT_SAMPLES);
+    // The later of the two pauses
+    assert_eq!(segment_cut(0, total, &pauses), Some(201_600));
+}
+
+#[test]
+fn test_segments_without_pauses_are_cut_before_whispers_limit() {
+    let mut pauses = PauseTracker::new(Box::new(LoudnessVad));
+    pauses.push(&speech(20.0));
+    assert_eq!(segment_cut(0, 20 * 16000, &pauses), None);
+
+    pauses.push(&speech(5.0));
+    assert_eq!(
+        segment_cut(0, HARD_SEGMENT_SAMPLES, &pauses),
+        Some(HARD_SEGMENT_SAMPLES - OVERLAP_SAMPLES)
+    );
+}
+
+fn pauses_total(secs: &[f32]) -> usize {
+    secs.iter().map(|s| (s * 16000.0) as usize).sum()
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+use vibeflow_lib::modules::devices::DeviceKind;
+use vibeflow_lib::modules::loopback::{
+    device_info, monitor_name, parse_default_sink, parse_sources, MixBus, MixSettings,
+};
+
+const PACTL_SOURCES: &str = "Source #55
+	State: SUSPENDED
+	Name: alsa_output.pci-0000_00_1f.3.analog-stereo.monitor
+	Description: Monitor of Built-in Audio Analog Stereo
+	Driver: PipeWire
+	Sample Specification: s32le 2ch 48000Hz
+	Channel Map: front-left,front-right
+Source #56
+	State: RUNNING
+	Name: alsa_input.usb-Q9_Microphone-00.mono-fallback
+	Description: Q9 Microphone Mono
+	Sample Specification: s16le 1ch 44100Hz
+Source #57
+	State: IDLE
+	Name: bluez_output.00_11_22_33_44_55.1.monitor
+	Description: Monitor of WH-1000XM4
+	Sample Specification: float32le 2ch 44100Hz
+";
+
+#[test]
+fn test_monitor_sources_from_pactl() {
+    let sources = parse_sources(PACTL_SOURCES);
+    // Microphones are cpal's business, only monitors are listed
+    assert_eq!(sources.len(), 2);
+    assert_eq!(
+        sources[0].name,
+        "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"
+    );
+    assert_eq!((sources[0].channels, sources[0].rate), (2, 48000));
+    assert_eq!(sources[1].format, "float32le");
+    assert_eq!(sources[1].rate, 44100);
+
+    let default_sink = parse_default_sink(
+        "Server Name: PulseAudio (on PipeWire 1.0.5)\nDefault Sink: bluez_output.00_11_22_33_44_55.1\nDefault Source: x\n",
+    );
+    let headphones = device_info(&sources[1], default_sink.as_deref());
+    assert_eq!(
+        headphones.id,
+        "loopback:bluez_output.00_11_22_33_44_55.1.monitor"
+    );
+    assert_eq!(headphones.display_name, "System audio (WH-1000XM4)");
+    assert_eq!(headphones.kind, DeviceKind::Loopback);
+    assert!(headphones.is_default);
+    assert!(!device_info(&sources[0], default_sink.as_deref()).is_default);
+
+    assert_eq!(
+        monitor_name(&headphones.id),
+        Some("bluez_output.00_11_22_33_44_55.1.monitor")
+    );
+    assert_eq!(monitor_name("alsa:default"), None);
+}
+
+#[test]
+fn test_mix_bus_applies_per_source_gain() {
+    let bus = Arc::new(MixBus::default());
+    let settings = MixSettings {
+        enabled: true,
+        monitor: None,
+        mic_gain_db: 0.0,
+        system_gain_db: -6.0206, // x0.5
+    };
+
+    // Not fed: the mic passes untouched
+    let mut mic = vec![0.2f32; 4];
+    bus.mix_into(&mut mic, &settings);
+    assert_eq!(mic, vec![0.2; 4]);
+
+    let guard = bus.attach();
+    bus.push(&[0.4, 0.4]);
+    let mut mic = vec![0.2f32; 4];
+    bus.mix_into(&mut mic, &settings);
+    // Two samples of system audio, then an underrun counts as silence
+    for (got, want) in mic.iter().zip([0.4, 0.4, 0.2, 0.2]) {
+        assert!((got - want).abs() < 1e-4, "{} vs {}", got, want);
+    }
+
+    // Feed stream gone: back to the mic alone, leftovers discarded
+    bus.push(&[0.4; 8]);
+    drop(guard);
+    assert!(!bus.is_active());
+    let mut mic = vec![0.2f32; 4];
+    bus.mix_into(&mut mic, &settings);
+    assert_eq!(mic, vec![0.2; 4]);
+}
+
+#[test]
+fn test_mix_bus_caps_lead() {
+    let bus = Arc::new(MixBus::default());
+    let _guard = bus.attach();
+    // 1 s of system audio while the mic stream delivers nothing
+    let ramp: Vec<f32> = (0..16000).map(|i| i as f32 / 16000.0).collect();
+    bus.push(&ramp);
+
+    let settings = MixSettings {
+        enabled: true,
+        mic_gain_db: 0.0,
+        system_gain_db: 0.0,
+        ..MixSettings::default()
+    };
+    let mut mic = vec![0.0f32; 1];
+    bus.mix_into(&mut mic, &settings);
+    // Only the newest 250 ms are kept
+    assert!((mic[0] - 12000.0 / 16000.0).abs() < 1e-6, "{}", mic[0]);
+}
@@ -0,0 +1,60 @@
+use vibeflow_lib::modules::meter::{Meter, MeterFrame, SPECTRUM_BANDS};
+
+fn sine(freq: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+    (0..(16000.0 * seconds) as usize)
+        .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / 16000.0).sin())
+        .collect()
+}
+
+fn frames(meter: &mut Meter, input: &[f32]) -> Vec<MeterFrame> {
+    input.chunks(160).filter_map(|b| meter.push(b)).collect()
+}
+
+#[test]
+fn test_meter_levels_are_calibrated() {
+    let mut meter = Meter::new();
+    let out = frames(&mut meter, &sine(1000.0, 0.5, 1.0));
+
+    // ~30 frames per second of audio
+    assert!((28..=32).contains(&out.len()), "{} frames", out.len());
+    let last = out.last().unwrap();
+    // 0.5 peak = -6 dBFS, RMS 3 dB lower
+    assert!(
+        (last.peak_dbfs + 6.0).abs() < 0.2,
+        "peak {}",
+        last.peak_dbfs
+    );
+    assert!((last.rms_dbfs + 9.0).abs() < 0.2, "rms {}", last.rms_dbfs);
+}
+
+#[test]
+fn test_spectrum_peaks_in_the_right_band() {
+    for (freq, expected_band) in [(150.0, 3), (1000.0, 9), (6000.0, 15)] {
+        let mut meter = Meter::new();
+        let last = frames(&mut meter, &sine(freq, 0.5, 0.5)).pop().unwrap();
+        assert_eq!(last.bands_dbfs.len(), SPECTRUM_BANDS);
+
+        let loudest = last
+            .bands_dbfs
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert_eq!(
+            loudest, expected_band,
+            "{} Hz landed in band {}",
+            freq, loudest
+        );
+        // Band level close to the tone's peak level (-6 dBFS)
+        assert!((last.bands_dbfs[loudest] + 6.0).abs() < 2.0);
+    }
+}
+
+#[test]
+fn test_silence_reads_as_floor() {
+    let mut meter = Meter::new();
+    let last = frames(&mut meter, &vec![0.0; 8000]).pop().unwrap();
+    assert_eq!(last.peak_dbfs, -100.0);
+    assert!(last.bands_dbfs.iter().all(|&b| b == -100.0));
+}
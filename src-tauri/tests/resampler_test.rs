@@ -0,0 +1,117 @@
+use vibeflow_lib::modules::resampler::StreamResampler;
+
+const TARGET_RATE: u32 = 16000;
+
+fn sine(rate: u32, freq: f32, seconds: f32) -> Vec<f32> {
+    let n = (rate as f32 * seconds) as usize;
+    (0..n)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin())
+        .collect()
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|&x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+// Feeds the input in irregular, callback-like chunk sizes
+fn process_chunked(resampler: &mut StreamResampler, input: &[f32]) -> Vec<f32> {
+    let sizes = [1, 7, 480, 1023, 13, 256, 2048, 441];
+    let mut output = Vec::new();
+    let mut pos = 0;
+    let mut i = 0;
+    while pos < input.len() {
+        let end = (pos + sizes[i % sizes.len()]).min(input.len());
+        output.extend(resampler.process(&input[pos..end]));
+        pos = end;
+        i += 1;
+    }
+    output
+}
+
+#[test]
+fn test_out_of_band_energy_is_rejected() {
+    for src_rate in [44100, 48000, 96000] {
+        // 12 kHz is above the 8 kHz Nyquist limit of the 16 kHz target.
+        // Linear interpolation folds it back to an audible 4 kHz alias.
+        let input = sine(src_rate, 12000.0, 1.0);
+        let mut resampler = StreamResampler::new(src_rate, TARGET_RATE).unwrap();
+        let output = resampler.process(&input);
+
+        // Skip the filter settling time at the start
+        let steady = &output[1600..output.len() - 1600];
+        let attenuation_db = 20.0 * (rms(steady) / rms(&input)).log10();
+        assert!(
+            attenuation_db < -50.0,
+            "Out-of-band tone leaked through at {} Hz ({:.1} dB)",
+            src_rate,
+            attenuation_db
+        );
+    }
+}
+
+#[test]
+fn test_in_band_energy_is_preserved() {
+    for src_rate in [44100, 48000, 96000] {
+        let input = sine(src_rate, 1000.0, 1.0);
+        let mut resampler = StreamResampler::new(src_rate, TARGET_RATE).unwrap();
+        let output = resampler.process(&input);
+
+        let steady = &output[1600..output.len() - 1600];
+        let gain_db = 20.0 * (rms(steady) / rms(&input)).log10();
+        assert!(gain_db.abs() < 0.5, "1 kHz tone changed level at {} Hz ({:.2} dB)", src_rate, gain_db);
+    }
+}
+
+#[test]
+fn test_chunk_boundaries_do_not_change_output() {
+    for src_rate in [44100, 48000] {
+        let input: Vec<f32> = sine(src_rate, 440.0, 1.0)
+            .iter()
+            .zip(sine(src_rate, 3100.0, 1.0))
+            .map(|(a, b)| a + b * 0.3)
+            .collect();
+
+        let mut one_shot = StreamResampler::new(src_rate, TARGET_RATE).unwrap();
+        let expected = one_shot.process(&input);
+
+        let mut streaming = StreamResampler::new(src_rate, TARGET_RATE).unwrap();
+        let actual = process_chunked(&mut streaming, &input);
+
+        assert_eq!(expected.len(), actual.len(), "Sample count depends on chunking at {} Hz", src_rate);
+        for (i, (a, b)) in expected.iter().zip(&actual).enumerate() {
+            assert!((a - b).abs() < 1e-5, "Sample {} differs at {} Hz: {} vs {}", i, src_rate, a, b);
+        }
+    }
+}
+
+#[test]
+fn test_timing_is_preserved_across_chunks() {
+    for src_rate in [44100, 48000, 96000] {
+        // Single click at exactly 0.5 s
+        let mut input = vec![0.0; src_rate as usize];
+        input[src_rate as usize / 2] = 1.0;
+
+        let mut resampler = StreamResampler::new(src_rate, TARGET_RATE).unwrap();
+        let output = process_chunked(&mut resampler, &input);
+
+        let peak = output
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .map(|(i, _)| i)
+            .unwrap();
+        let expected = TARGET_RATE as usize / 2;
+        assert!(
+            peak.abs_diff(expected) <= 1,
+            "Click moved from {} to {} at {} Hz",
+            expected,
+            peak,
+            src_rate
+        );
+
+        // No samples lost or invented: output length tracks input duration
+        // (minus at most one pending input chunk of 10 ms)
+        assert!(output.len() <= expected * 2);
+        assert!(output.len() + (TARGET_RATE as usize / 100) * 2 >= expected * 2);
+    }
+}
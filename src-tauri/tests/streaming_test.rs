@@ -0,0 +1,120 @@
+use vibeflow_lib::modules::streaming::{LocalAgreement, PartialTranscript, Word, WordBuilder};
+
+// One word per 0.5 s, starting at `start`
+fn words(start: usize, texts: &[&str]) -> Vec<Word> {
+    texts
+        .iter()
+        .enumerate()
+        .map(|(i, t)| Word {
+            text: format!(" {}", t),
+            start: start + i * 8000,
+            end: start + (i + 1) * 8000,
+            probability: 0.9,
+        })
+        .collect()
+}
+
+fn text(words: &[Word]) -> String {
+    words
+        .iter()
+        .map(|w| w.text.as_str())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[test]
+fn test_commits_what_two_passes_agree_on() {
+    let mut stream = LocalAgreement::default();
+
+    let p = stream.insert(words(0, &["hello", "word"]));
+    assert_eq!(p.committed, "");
+    assert_eq!(p.tentative, "hello word");
+
+    // Case and punctuation changes still agree
+    let p = stream.insert(words(0, &["Hello,", "world", "how"]));
+    assert_eq!(p.committed, "Hello,");
+    assert_eq!(p.tentative, " world how");
+    // The next pass starts after the committed word
+    assert_eq!(stream.window_start(), 8000);
+    assert_eq!(stream.prompt(), "Hello,");
+}
+
+#[test]
+fn test_committed_text_is_not_decoded_again() {
+    let mut stream = LocalAgreement::default();
+    stream.insert(words(0, &["one", "two", "three"]));
+    stream.insert(words(0, &["one", "two", "four"]));
+    assert_eq!(stream.window_start(), 16000);
+
+    // Whisper repeats the last committed word at the start of the new window
+    let p = stream.insert(words(16000, &["two", "four", "five"]));
+    assert_eq!(p.committed, "one two four");
+    assert_eq!(p.tentative, " five");
+    assert_eq!(stream.window_start(), 32000);
+
+    let p = stream.insert(words(32000, &["fives", "six"]));
+    assert_eq!(p.committed, "one two four");
+    assert_eq!(p.tentative, " fives six");
+}
+
+#[test]
+fn test_finish_appends_the_last_window() {
+    let mut stream = LocalAgreement::default();
+    stream.insert(words(0, &["one", "two"]));
+    stream.insert(words(0, &["one", "two", "three"]));
+    let all = stream.finish(Some(words(16000, &["two", "three", "four."])));
+    assert_eq!(text(&all), "one two three four.");
+    assert_eq!(all.last().unwrap().end, 40000);
+
+    // No audio left after the committed words: the last guess stands
+    let mut stream = LocalAgreement::default();
+    stream.insert(words(0, &["one"]));
+    stream.insert(words(0, &["one", "two"]));
+    assert_eq!(text(&stream.finish(None)), "one two");
+}
+
+#[test]
+fn test_cut_commits_words_before_the_cut() {
+    let mut stream = LocalAgreement::default();
+    stream.insert(words(0, &["a", "b"]));
+    stream.insert(words(0, &["c", "d"]));
+    // Final pass over the window plus overlap: "f" starts after the cut at 20000
+    let p = stream.cut(words(0, &["c", "d", "e", "f"]), 20000);
+    assert_eq!(
+        p,
+        PartialTranscript {
+            committed: "c d e".to_string(),
+            tentative: String::new()
+        }
+    );
+    // "e" crosses the cut, so the next window starts after it
+    assert_eq!(stream.window_start(), 24000);
+
+    // Nothing heard in the window: skip it
+    let mut stream = LocalAgreement::default();
+    stream.insert(Vec::new());
+    stream.cut(Vec::new(), 40000);
+    assert_eq!(stream.window_start(), 40000);
+}
+
+#[test]
+fn test_word_builder_joins_tokens_and_split_characters() {
+    let mut b = WordBuilder::default();
+    b.push_token(b" Gr", 0, 10, 0.9);
+    b.push_token("ü".as_bytes()[..1].as_ref(), 10, 20, 0.8);
+    b.push_token("ü".as_bytes()[1..].as_ref(), 20, 30, 0.95);
+    b.push_token(b"\xc3\x9fe", 30, 40, 0.3);
+    b.push_token(b",", 40, 45, 0.99);
+    b.push_token(b" world", 50, 90, 0.7);
+
+    let words = b.finish();
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[0].text, " Grüße,");
+    assert_eq!((words[0].start, words[0].end), (0, 45));
+    // The least certain token decides
+    assert_eq!(words[0].probability, 0.3);
+    assert_eq!(words[1].text, " world");
+    assert_eq!((words[1].start, words[1].end), (50, 90));
+    assert_eq!(words[1].probability, 0.7);
+}
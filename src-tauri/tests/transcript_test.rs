@@ -0,0 +1,132 @@
+use vibeflow_lib::modules::streaming::Word;
+use vibeflow_lib::modules::transcript::{
+    speech_ms, Rejection, SensitiveTranscript, TranscriptSettings,
+};
+
+// One word per 0.5 s from the session start, all with `probability`
+fn words(texts: &[&str], probability: f32) -> Vec<Word> {
+    texts
+        .iter()
+        .enumerate()
+        .map(|(i, t)| Word {
+            text: format!(" {}", t),
+            start: i * 8000,
+            end: (i + 1) * 8000,
+            probability,
+        })
+        .collect()
+}
+
+fn transcript(texts: &[&str], probability: f32, speech_ms: u64) -> SensitiveTranscript {
+    SensitiveTranscript::from_words(
+        words(texts, probability),
+        Some("en".into()),
+        false,
+        Some(speech_ms),
+    )
+}
+
+#[test]
+fn test_streamed_words_are_split_into_sentences() {
+    let mut w = words(&["Hello", "there.", "How", "are", "you?"], 0.9);
+    w[1].probability = 0.2;
+    let t = SensitiveTranscript::from_words(w, Some("en".into()), false, Some(2500));
+
+    assert_eq!(t.as_str(), "Hello there. How are you?");
+    assert_eq!(t.language(), Some("en"));
+    assert_eq!(t.segments().len(), 2);
+    assert_eq!(t.segments()[0].text, "Hello there.");
+    assert_eq!(
+        (t.segments()[1].start_ms, t.segments()[1].end_ms),
+        (1000, 2500)
+    );
+
+    let words: Vec<_> = t.words().map(|w| w.text.as_str()).collect();
+    assert_eq!(words, ["Hello", "there.", "How", "are", "you?"]);
+    assert_eq!(t.words().nth(2).unwrap().start_ms, 1000);
+
+    let low: Vec<_> = t.low_confidence_words().map(|w| w.text.as_str()).collect();
+    assert_eq!(low, ["there."]);
+    assert!((t.confidence().unwrap() - 0.76).abs() < 1e-6);
+}
+
+#[test]
+fn test_plain_text_has_no_details_and_is_not_judged() {
+    let t = SensitiveTranscript::new("Error: model not found".into());
+    assert_eq!(t.as_str(), "Error: model not found");
+    assert!(t.segments().is_empty());
+    assert_eq!(t.confidence(), None);
+    assert_eq!(t.rejection(), None);
+}
+
+#[test]
+fn test_garbage_is_rejected() {
+    // Confident speech passes, even when it is short
+    assert_eq!(transcript(&["Thank", "you."], 0.9, 1000).rejection(), None);
+    assert_eq!(transcript(&["Yes."], 0.9, 0).rejection(), None);
+
+    // What Whisper makes up on silence
+    assert_eq!(
+        transcript(&["Thanks", "for", "watching!"], 0.9, 1000).rejection(),
+        Some(Rejection::Hallucination)
+    );
+    assert_eq!(
+        transcript(&["Thank", "you."], 0.9, 0).rejection(),
+        Some(Rejection::Hallucination)
+    );
+    assert_eq!(
+        transcript(&["[BLANK_AUDIO]"], 0.9, 1000).rejection(),
+        Some(Rejection::Hallucination)
+    );
+    assert_eq!(
+        transcript(&["(music)"], 0.9, 0).rejection(),
+        Some(Rejection::Hallucination)
+    );
+
+    // Decoding loops
+    let looped = ["so", "I", "think", "we", "we", "we", "we", "we", "we"];
+    assert_eq!(
+        transcript(&looped, 0.9, 1000).rejection(),
+        Some(Rejection::Repetition)
+    );
+    let looped = [
+        "and", "then", "and", "then", "and", "then", "and", "then", "and", "then",
+    ];
+    assert_eq!(
+        transcript(&looped, 0.9, 1000).rejection(),
+        Some(Rejection::Repetition)
+    );
+
+    // Unsure text over little speech, or just very unsure text
+    assert_eq!(
+        transcript(&["the", "cat"], 0.4, 0).rejection(),
+        Some(Rejection::NoSpeech)
+    );
+    assert_eq!(transcript(&["the", "cat"], 0.4, 1000).rejection(), None);
+    assert_eq!(
+        transcript(&["the", "cat"], 0.1, 1000).rejection(),
+        Some(Rejection::LowConfidence)
+    );
+}
+
+#[test]
+fn test_short_dictation_is_kept_when_speech_is_unknown() {
+    // Rejection is opt-in
+    assert!(!TranscriptSettings::default().reject_garbage);
+
+    // No VAD figure (e.g. plain text): "Thanks." is taken as dictated
+    let t = SensitiveTranscript::from_words(words(&["Thanks."], 0.9), None, false, None);
+    assert_eq!(t.speech_ms(), None);
+    assert_eq!(t.rejection(), None);
+    assert_eq!(transcript(&["Thanks."], 0.9, 400).rejection(), None);
+    assert_eq!(
+        transcript(&["Thanks."], 0.9, 100).rejection(),
+        Some(Rejection::Hallucination)
+    );
+}
+
+#[test]
+fn test_speech_ms_from_vad_samples() {
+    assert_eq!(speech_ms(0), 0);
+    assert_eq!(speech_ms(8000), 500);
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+use vibeflow_lib::modules::vad::{
+    create_detector, AutoStopDetector, SileroVad, VadBackend, VadSettings, VoiceActivityDetector,
+    WebRtcVad,
+};
+
+// A voiced vowel: 120 Hz with decaying harmonics
+fn vowel(seconds: f32) -> Vec<f32> {
+    (0..(16000.0 * seconds) as usize)
+        .map(|i| {
+            let t = i as f32 / 16000.0;
+            (1..=10)
+                .map(|h| {
+                    let h = h as f32;
+                    0.3 / h * (2.0 * std::f32::consts::PI * 120.0 * h * t).sin()
+                })
+                .sum::<f32>()
+        })
+        .collect()
+}
+
+fn silence(seconds: f32) -> Vec<f32> {
+    vec![0.0; (16000.0 * seconds) as usize]
+}
+
+fn speech_frames(vad: &mut dyn VoiceActivityDetector, samples: &[f32]) -> usize {
+    samples
+        .chunks_exact(vad.frame_size())
+        .filter(|frame| vad.is_speech(frame))
+        .count()
+}
+
+#[test]
+fn test_webrtc_detector_classifies_frames() {
+    let mut vad = WebRtcVad::new(2);
+    assert_eq!(vad.frame_size(), 480);
+    assert_eq!(speech_frames(&mut vad, &silence(1.0)), 0);
+    assert_eq!(speech_frames(&mut vad, &vowel(1.0)), 33);
+
+    // Forgets the speech it just heard (WebRTC has its own hangover)
+    vad.reset();
+    assert_eq!(speech_frames(&mut vad, &silence(1.0)), 0);
+}
+
+#[test]
+fn test_backend_selection_falls_back_to_webrtc() {
+    let missing = Path::new("/nonexistent/vibeflow");
+    assert!(SileroVad::load(&missing.join("silero_vad.onnx"), 0.5).is_err());
+
+    let webrtc = create_detector(&VadSettings::default(), missing);
+    assert_eq!(webrtc.frame_size(), 480);
+
+    // Without the model file Silero can't load, so auto-stop keeps working on WebRTC
+    let settings = VadSettings {
+        backend: VadBackend::Silero,
+        ..VadSettings::default()
+    };
+    let mut fallback = create_detector(&settings, missing);
+    assert_eq!(fallback.frame_size(), 480);
+    assert!(speech_frames(fallback.as_mut(), &vowel(0.5)) > 0);
+}
+
+#[test]
+fn test_auto_stop_after_trailing_silence() {
+    let settings = VadSettings::default();
+    let mut detector = AutoStopDetector::new(&settings, std::env::temp_dir());
+
+    // Silence alone never stops a recording
+    assert!(!detector.push(&silence(3.0)));
+
+    assert!(!detector.push(&vowel(1.0)));
+    // Hangover (300 ms) + timeout (1500 ms) of silence
+    assert!(!detector.push(&silence(1.0)));
+    assert!(detector.push(&silence(1.0)));
+
+    // A new session starts over
+    detector.configure(&settings);
+    assert!(!detector.push(&silence(3.0)));
+}
+
+#[test]
+fn test_backend_change_keeps_detecting() {
+    let mut detector = AutoStopDetector::new(&VadSettings::default(), std::env::temp_dir());
+    let silero = VadSettings {
+        backend: VadBackend::Silero,
+        silence_timeout_ms: 500,
+        ..VadSettings::default()
+    };
+    // Loads in the background; the WebRTC detector (or its replacement) keeps going
+    detector.configure(&silero);
+    assert!(!detector.push(&vowel(1.0)));
+    assert!(detector.push(&silence(1.0)));
+}
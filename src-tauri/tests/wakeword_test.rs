@@ -0,0 +1,96 @@
+use anyhow::Result;
+use std::path::Path;
+use vibeflow_lib::modules::wakeword::{
+    model_path, KeywordSpotter, WakeWordDetector, WakeWordListener, WakeWordSettings,
+    WAKE_WINDOW_SAMPLES,
+};
+
+// Scores a window by the RMS of its newest 100 ms, so a loud burst plays the phrase
+struct LoudnessSpotter;
+
+impl KeywordSpotter for LoudnessSpotter {
+    fn score(&mut self, window: &[f32]) -> Result<f32> {
+        let tail = &window[window.len() - 1600..];
+        let rms = (tail.iter().map(|s| s * s).sum::<f32>() / tail.len() as f32).sqrt();
+        Ok(rms)
+    }
+}
+
+// Constant level, so every hop of one section scores exactly the same
+fn level(samples: usize, amplitude: f32) -> Vec<f32> {
+    vec![amplitude; samples]
+}
+
+#[test]
+fn test_detects_once_and_reports_audio_after_the_phrase() {
+    let mut detector = WakeWordDetector::new(Box::new(LoudnessSpotter), 0.3);
+
+    // Quiet lead-in fills the window without triggering
+    let mut heard = Vec::new();
+    for block in level(WAKE_WINDOW_SAMPLES + 8000, 0.01).chunks(160) {
+        heard.extend(detector.push(block));
+    }
+    assert!(heard.is_empty());
+
+    // "Phrase" (loud 300 ms), then quiet speech-level audio in odd-sized blocks
+    for block in level(4800, 0.8).chunks(160) {
+        heard.extend(detector.push(block));
+    }
+    let mut after = 0;
+    for block in level(8000, 0.01).chunks(317) {
+        if let Some(d) = detector.push(block) {
+            heard.push(d);
+            after = 0;
+        }
+        after += block.len();
+    }
+
+    assert_eq!(heard.len(), 1, "phrase should trigger exactly once");
+    let d = heard[0];
+    assert!(d.score > 0.5);
+    // The peak is the last loud hop, so only the quiet tail is kept
+    // (detection comes one hop after the peak, when the score drops)
+    assert!(d.keep_samples >= 1600 && d.keep_samples <= 1600 + 317);
+    assert!(after > 0);
+}
+
+#[test]
+fn test_reset_forgets_buffered_audio() {
+    let mut detector = WakeWordDetector::new(Box::new(LoudnessSpotter), 0.3);
+    for block in level(WAKE_WINDOW_SAMPLES, 0.8).chunks(400) {
+        assert!(detector.push(block).is_none(), "window not full yet");
+    }
+    detector.reset();
+    // After a reset the window has to fill again before anything is scored
+    for block in level(WAKE_WINDOW_SAMPLES - 1600, 0.8).chunks(400) {
+        assert!(detector.push(block).is_none());
+    }
+}
+
+#[test]
+fn test_model_path_per_phrase() {
+    let dir = Path::new("/data");
+    assert_eq!(
+        model_path(dir, "Hey VibeFlow"),
+        dir.join("wakeword_hey_vibeflow.onnx")
+    );
+    assert_eq!(
+        model_path(dir, &WakeWordSettings::default().phrase),
+        dir.join("wakeword_hey_vibeflow.onnx")
+    );
+}
+
+#[test]
+fn test_listener_without_model_stays_quiet() {
+    let mut listener = WakeWordListener::new(std::env::temp_dir().join("vibeflow_no_models"));
+    let settings = WakeWordSettings {
+        enabled: true,
+        ..WakeWordSettings::default()
+    };
+    // The missing model is looked up on the spotter thread, not here
+    for block in level(WAKE_WINDOW_SAMPLES * 3, 0.8).chunks(160) {
+        assert!(listener.push(&settings, block).is_none());
+    }
+    listener.session_finished(&settings);
+    assert!(listener.push(&settings, &level(160, 0.8)).is_none());
+}